@@ -0,0 +1,218 @@
+use near_contract_standards::fungible_token::core::ext_ft_core;
+use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+use near_contract_standards::fungible_token::Balance;
+use near_sdk::borsh::to_vec;
+use near_sdk::collections::LookupMap;
+use near_sdk::json_types::{Base64VecU8, U128};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{assert_one_yocto, env, log, near, require, serde_json, AccountId, BorshStorageKey, Gas, NearToken, PanicOnDefault, Promise, PromiseOrValue, PromiseResult};
+
+#[derive(BorshStorageKey)]
+#[near]
+enum StorageKey {
+    Rounds,
+    ClaimedBitmap,
+}
+
+#[near(serializers = [borsh, json])]
+pub struct AirdropRound {
+    pub merkle_root: Base64VecU8,
+    pub total_amount: U128,
+    pub claimed_amount: U128,
+    /// Timestamp in milliseconds after which claims close and the owner can sweep
+    pub expires_at: u64,
+    pub swept: bool,
+}
+
+/// `ft_transfer_call` message of the owner funding a new round
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CreateRoundMessage {
+    pub merkle_root: Base64VecU8,
+    pub expires_at: u64,
+}
+
+/// Leaf of the Merkle tree, `sha256(borsh(index, account_id, amount))`
+pub fn leaf_hash(index: u64, account_id: &AccountId, amount: Balance) -> Vec<u8> {
+    return env::sha256(&to_vec(&(index, account_id, amount)).unwrap());
+}
+
+/// Pairs are hashed in sorted order, so proofs don't need to carry the sibling position
+pub fn verify_proof(leaf: Vec<u8>, proof: &[Base64VecU8], merkle_root: &[u8]) -> bool {
+    let mut hash = leaf;
+    for sibling in proof {
+        let sibling = &sibling.0;
+        hash = if hash.as_slice() <= sibling.as_slice() {
+            env::sha256(&[hash.as_slice(), sibling].concat())
+        } else {
+            env::sha256(&[sibling.as_slice(), hash.as_slice()].concat())
+        };
+    }
+    return hash == merkle_root;
+}
+
+#[near(contract_state)]
+#[derive(PanicOnDefault)]
+pub struct AirdropContract {
+    pub owner_id: AccountId,
+    pub token_id: AccountId,
+    pub rounds: LookupMap<u64, AirdropRound>,
+    pub round_count: u64,
+    /// 128 claim bits per entry, keyed by round and `index / 128`
+    pub claimed_bitmap: LookupMap<(u64, u64), u128>,
+}
+
+#[near]
+impl AirdropContract {
+    #[init]
+    pub fn new(owner_id: AccountId, token_id: AccountId) -> Self {
+        return Self {
+            owner_id,
+            token_id,
+            rounds: LookupMap::new(StorageKey::Rounds),
+            round_count: 0,
+            claimed_bitmap: LookupMap::new(StorageKey::ClaimedBitmap),
+        };
+    }
+
+    fn internal_create_round(&mut self, amount: Balance, message: CreateRoundMessage) -> Result<u64, String> {
+        if message.merkle_root.0.len() != 32 {
+            return Err("Merkle root has to be 32 bytes".to_string());
+        }
+        if message.expires_at <= env::block_timestamp_ms() {
+            return Err("Expiration must be in the future".to_string());
+        }
+
+        let round_id = self.round_count;
+        self.rounds.insert(&round_id, &AirdropRound {
+            merkle_root: message.merkle_root,
+            total_amount: amount.into(),
+            claimed_amount: U128(0),
+            expires_at: message.expires_at,
+            swept: false,
+        });
+        self.round_count += 1;
+
+        log!("[create_round] round_id: {}, amount: {}", round_id, amount);
+        return Ok(round_id);
+    }
+
+    fn internal_set_claimed(&mut self, round_id: u64, index: u64, claimed: bool) {
+        let key = (round_id, index / 128);
+        let bit = 1u128 << (index % 128);
+        let word = self.claimed_bitmap.get(&key).unwrap_or(0);
+        self.claimed_bitmap.insert(&key, &if claimed { word | bit } else { word & !bit });
+    }
+
+    /// Claims `amount` for the caller, `index` is the position of the caller's leaf in the round
+    pub fn claim(&mut self, round_id: u64, index: u64, amount: U128, proof: Vec<Base64VecU8>) -> Promise {
+        let account_id = env::predecessor_account_id();
+        let mut round = self.rounds.get(&round_id).expect("Round not found");
+        require!(env::block_timestamp_ms() < round.expires_at, "Round expired");
+        require!(!self.is_claimed(round_id, index), "Already claimed");
+        require!(verify_proof(leaf_hash(index, &account_id, amount.0), &proof, &round.merkle_root.0), "Invalid proof");
+
+        round.claimed_amount = (round.claimed_amount.0 + amount.0).into();
+        require!(round.claimed_amount.0 <= round.total_amount.0, "Claims exceed the round amount");
+        self.rounds.insert(&round_id, &round);
+        self.internal_set_claimed(round_id, index, true);
+
+        return ext_ft_core::ext(self.token_id.clone())
+            .with_attached_deposit(NearToken::from_yoctonear(1))
+            .with_static_gas(Gas::from_tgas(20))
+            .ft_transfer(account_id, amount, Some(format!("Airdrop round {}", round_id)))
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(Gas::from_tgas(10))
+                    .callback_claim(round_id, index, amount)
+            );
+    }
+
+    /// Transfers the unclaimed tokens of an expired round to the owner
+    #[payable]
+    pub fn sweep(&mut self, round_id: u64) -> Promise {
+        assert_one_yocto();
+        require!(env::predecessor_account_id() == self.owner_id, "Only owner can sweep");
+
+        let mut round = self.rounds.get(&round_id).expect("Round not found");
+        require!(env::block_timestamp_ms() >= round.expires_at, "Round is not expired");
+        require!(!round.swept, "Round is already swept");
+
+        let unclaimed_amount = round.total_amount.0 - round.claimed_amount.0;
+        round.swept = true;
+        self.rounds.insert(&round_id, &round);
+
+        return ext_ft_core::ext(self.token_id.clone())
+            .with_attached_deposit(NearToken::from_yoctonear(1))
+            .with_static_gas(Gas::from_tgas(20))
+            .ft_transfer(self.owner_id.clone(), U128(unclaimed_amount), Some(format!("Sweep airdrop round {}", round_id)))
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(Gas::from_tgas(10))
+                    .callback_sweep(round_id)
+            );
+    }
+
+    #[private]
+    pub fn callback_claim(&mut self, round_id: u64, index: u64, amount: U128) {
+        const TRANSFER_PROMISE_INDEX: u64 = 0;
+        if let PromiseResult::Successful(_) = env::promise_result(TRANSFER_PROMISE_INDEX) {
+            return;
+        }
+
+        log!("[callback_claim] transfer failed, round_id: {}, index: {}", round_id, index);
+        let mut round = self.rounds.get(&round_id).unwrap();
+        round.claimed_amount = (round.claimed_amount.0 - amount.0).into();
+        self.rounds.insert(&round_id, &round);
+        self.internal_set_claimed(round_id, index, false);
+    }
+
+    #[private]
+    pub fn callback_sweep(&mut self, round_id: u64) {
+        const TRANSFER_PROMISE_INDEX: u64 = 0;
+        if let PromiseResult::Successful(_) = env::promise_result(TRANSFER_PROMISE_INDEX) {
+            return;
+        }
+
+        log!("[callback_sweep] transfer failed, round_id: {}", round_id);
+        let mut round = self.rounds.get(&round_id).unwrap();
+        round.swept = false;
+        self.rounds.insert(&round_id, &round);
+    }
+}
+
+#[near]
+impl AirdropContract {
+    pub fn get_round(&self, round_id: u64) -> Option<AirdropRound> {
+        return self.rounds.get(&round_id);
+    }
+
+    pub fn get_round_count(&self) -> u64 {
+        return self.round_count;
+    }
+
+    pub fn is_claimed(&self, round_id: u64, index: u64) -> bool {
+        let word = self.claimed_bitmap.get(&(round_id, index / 128)).unwrap_or(0);
+        return word & (1u128 << (index % 128)) != 0;
+    }
+}
+
+#[near]
+impl FungibleTokenReceiver for AirdropContract {
+    /// Funds a new round, returns the whole `amount` as unused when the round can't be created
+    fn ft_on_transfer(&mut self, sender_id: AccountId, amount: U128, msg: String) -> PromiseOrValue<U128> {
+        require!(env::predecessor_account_id() == self.token_id, "Invalid token");
+        require!(sender_id == self.owner_id, "Only owner can fund rounds");
+
+        let result = serde_json::from_str::<CreateRoundMessage>(&msg)
+            .map_err(|err| err.to_string())
+            .and_then(|message| self.internal_create_round(amount.into(), message));
+
+        if let Err(err) = result {
+            log!("[ft_on_transfer] failed: {}", err);
+            return PromiseOrValue::Value(amount);
+        }
+
+        return PromiseOrValue::Value(U128(0));
+    }
+}
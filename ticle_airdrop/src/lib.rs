@@ -0,0 +1,105 @@
+#![allow(clippy::needless_return)]
+
+pub mod airdrop;
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+    use near_sdk::json_types::{Base64VecU8, U128};
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::{env, serde_json, testing_env, NearToken};
+
+    fn hash_pair(a: &[u8], b: &[u8]) -> Vec<u8> {
+        return if a <= b { env::sha256(&[a, b].concat()) } else { env::sha256(&[b, a].concat()) };
+    }
+
+    /// Tree of four leaves for accounts 2..=5, returns the root and the proof of each leaf
+    fn build_tree(amounts: [u128; 4]) -> (Vec<u8>, Vec<Vec<Base64VecU8>>) {
+        let leaves: Vec<Vec<u8>> = (0..4).map(|index| airdrop::leaf_hash(index as u64, &accounts(index + 2), amounts[index])).collect();
+        let left = hash_pair(&leaves[0], &leaves[1]);
+        let right = hash_pair(&leaves[2], &leaves[3]);
+        let root = hash_pair(&left, &right);
+
+        let proofs = vec![
+            vec![leaves[1].clone().into(), right.clone().into()],
+            vec![leaves[0].clone().into(), right.clone().into()],
+            vec![leaves[3].clone().into(), left.clone().into()],
+            vec![leaves[2].clone().into(), left.clone().into()],
+        ];
+        return (root, proofs);
+    }
+
+    fn setup(amounts: [u128; 4]) -> (airdrop::AirdropContract, Vec<Vec<Base64VecU8>>) {
+        let mut contract = airdrop::AirdropContract::new(accounts(0), accounts(1));
+        let (root, proofs) = build_tree(amounts);
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(1))
+            .build());
+        let msg = serde_json::json!({ "merkle_root": Base64VecU8::from(root), "expires_at": 1_000 });
+        let _ = contract.ft_on_transfer(accounts(0), amounts.iter().sum::<u128>().into(), msg.to_string());
+        assert_eq!(contract.get_round_count(), 1);
+
+        return (contract, proofs);
+    }
+
+    #[test]
+    fn test_claim() {
+        let (mut contract, proofs) = setup([10, 20, 30, 40]);
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(4))
+            .build());
+        contract.claim(0, 2, U128(30), proofs[2].clone());
+
+        assert!(contract.is_claimed(0, 2));
+        assert!(!contract.is_claimed(0, 1));
+        assert_eq!(contract.get_round(0).unwrap().claimed_amount, U128(30));
+    }
+
+    #[test]
+    #[should_panic(expected = "Already claimed")]
+    fn test_claim_twice() {
+        let (mut contract, proofs) = setup([10, 20, 30, 40]);
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.claim(0, 0, U128(10), proofs[0].clone());
+        contract.claim(0, 0, U128(10), proofs[0].clone());
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid proof")]
+    fn test_claim_wrong_amount() {
+        let (mut contract, proofs) = setup([10, 20, 30, 40]);
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.claim(0, 0, U128(11), proofs[0].clone());
+    }
+
+    #[test]
+    fn test_sweep() {
+        let (mut contract, proofs) = setup([10, 20, 30, 40]);
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.claim(0, 1, U128(20), proofs[1].clone());
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .block_timestamp(1_000 * 1_000_000)
+            .build());
+        contract.sweep(0);
+
+        let round = contract.get_round(0).unwrap();
+        assert!(round.swept);
+        assert_eq!(round.total_amount.0 - round.claimed_amount.0, 80);
+    }
+}
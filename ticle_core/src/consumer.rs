@@ -0,0 +1,203 @@
+use near_sdk::borsh;
+use near_sdk::collections::UnorderedMap;
+
+use crate::signer::PayloadSignature;
+use crate::*;
+
+/// Time the gateway has to redeem outstanding vouchers before a withdrawal can be claimed
+const CONSUMER_WITHDRAWAL_NOTICE_PERIOD: u64 = 24 * 60 * 60 * 1_000;
+
+#[near(serializers = [borsh])]
+pub struct ConsumerInfo {
+    balance: Balance,
+    /// Credit requested for withdrawal, vouchers can still be redeemed against it during the notice period
+    withdrawing_amount: Balance,
+    withdrawal_start_timestamp: u64,
+    vapis: UnorderedMap<String, ConsumerVAPIInfo>,
+}
+
+/// Usage of a VAPI already paid by the consumer through vouchers
+#[near(serializers = [borsh, json])]
+pub struct ConsumerVAPIInfo {
+    pub redeemed_amount: Balance,
+    pub nonce: u64,
+    /// Cap on `redeemed_amount` set by the consumer, unlimited when `None`
+    pub spending_limit: Option<Balance>,
+}
+
+#[near(serializers = [json])]
+pub struct ConsumerInfoResponse {
+    pub balance: Balance,
+    pub withdrawing_amount: Balance,
+    pub withdrawal_start_timestamp: u64,
+    pub vapis: Vec<(String, ConsumerVAPIInfo)>,
+}
+
+/// Issued by the gateway for the total usage of `vapi_id` by `consumer_id`, so only the
+/// latest voucher needs to be redeemed. The signers sign the borsh serialization of the voucher.
+#[near(serializers = [borsh, json])]
+pub struct UsageVoucher {
+    /// The core contract the voucher is issued for, so it can't be replayed against another deployment
+    pub contract_id: AccountId,
+    pub consumer_id: AccountId,
+    pub vapi_id: String,
+    pub cumulative_amount: U128,
+    pub nonce: u64,
+}
+
+impl TicleCore {
+    pub(crate) fn internal_fund_consumer(&mut self, consumer_id: &AccountId, amount: Balance) -> Result<(), String> {
+        log!("[internal_fund_consumer] consumer_id: {}, amount: {}", consumer_id, amount);
+        if !self.accounts.contains_key(consumer_id) {
+            return Err(format!("The account {} is not registered", consumer_id));
+        }
+
+        let mut consumer_info = match self.consumers.get(consumer_id) {
+            Some(consumer_info) => consumer_info,
+            None => {
+                let initial_storage_usage = env::storage_usage();
+                let consumer_info = ConsumerInfo {
+                    balance: 0,
+                    withdrawing_amount: 0,
+                    withdrawal_start_timestamp: 0,
+                    vapis: UnorderedMap::new([b"cv".as_slice(), &env::sha256(consumer_id.as_bytes())].concat()),
+                };
+                self.consumers.insert(consumer_id, &consumer_info);
+                if let Err(err) = self.internal_try_update_storage_usage(consumer_id, initial_storage_usage) {
+                    self.consumers.remove(consumer_id);
+                    return Err(err);
+                }
+                consumer_info
+            }
+        };
+
+        consumer_info.balance += amount;
+        self.consumers.insert(consumer_id, &consumer_info);
+        return Ok(());
+    }
+}
+
+#[near]
+impl TicleCore {
+    /// Debits the usage of the voucher not redeemed yet from the consumer's balance and distributes it
+    /// like a settlement. Callable by anyone, a voucher is only redeemable once its nonce is the highest seen.
+    /// The first redemption for a VAPI adds its usage record, charged to the caller's storage deposit
+    /// so a consumer can't block redemptions by withdrawing theirs.
+    pub fn redeem_voucher(&mut self, voucher: UsageVoucher, signatures: Vec<PayloadSignature>) -> Promise {
+        log!("[redeem_voucher] consumer_id: {}, vapi_id: {}, nonce: {}", voucher.consumer_id, voucher.vapi_id, voucher.nonce);
+        require!(voucher.contract_id == env::current_account_id(), "Voucher is issued for another contract");
+        require!(self.vapis.contains_key(&voucher.vapi_id), "Vertical API not found");
+        let mut consumer_info = self.consumers.get(&voucher.consumer_id).expect("Consumer not found");
+        let mut consumer_vapi_info = consumer_info.vapis.get(&voucher.vapi_id).unwrap_or(ConsumerVAPIInfo {
+            redeemed_amount: 0,
+            nonce: 0,
+            spending_limit: None,
+        });
+
+        require!(voucher.nonce > consumer_vapi_info.nonce, "Voucher nonce is already used");
+        require!(voucher.cumulative_amount.0 > consumer_vapi_info.redeemed_amount, "Voucher has nothing to redeem");
+        let amount = voucher.cumulative_amount.0 - consumer_vapi_info.redeemed_amount;
+        require!(consumer_vapi_info.spending_limit.is_none_or(|spending_limit| voucher.cumulative_amount.0 <= spending_limit), "Spending limit exceeded");
+        require!(consumer_info.balance + consumer_info.withdrawing_amount >= amount, "Consumer balance is not enough");
+
+        let message = borsh::to_vec(&voucher).unwrap();
+        self.internal_verify_signatures(&message, &signatures, amount).unwrap_or_else(|err| env::panic_str(&err));
+
+        // Credit pending withdrawal is only used once the balance is exhausted
+        let balance_amount = amount.min(consumer_info.balance);
+        consumer_info.balance -= balance_amount;
+        consumer_info.withdrawing_amount -= amount - balance_amount;
+        consumer_vapi_info.redeemed_amount = voucher.cumulative_amount.0;
+        consumer_vapi_info.nonce = voucher.nonce;
+
+        let initial_storage_usage = env::storage_usage();
+        consumer_info.vapis.insert(&voucher.vapi_id, &consumer_vapi_info);
+        self.consumers.insert(&voucher.consumer_id, &consumer_info);
+        self.internal_update_storage_usage(&env::predecessor_account_id(), initial_storage_usage);
+
+        let distribution = self.internal_distribute(&[voucher.vapi_id], &[amount]);
+        return self.internal_burn(distribution.burn_amount);
+    }
+
+    /// Limits the total usage of `vapi_id` redeemable from the caller's credit, `None` removes the limit
+    pub fn set_spending_limit(&mut self, vapi_id: String, spending_limit: Option<U128>) {
+        let consumer_id = env::predecessor_account_id();
+        require!(self.vapis.contains_key(&vapi_id), "Vertical API not found");
+        let mut consumer_info = self.consumers.get(&consumer_id).expect("Consumer not found");
+        let mut consumer_vapi_info = consumer_info.vapis.get(&vapi_id).unwrap_or(ConsumerVAPIInfo {
+            redeemed_amount: 0,
+            nonce: 0,
+            spending_limit: None,
+        });
+        consumer_vapi_info.spending_limit = spending_limit.map(|spending_limit| spending_limit.0);
+
+        let initial_storage_usage = env::storage_usage();
+        consumer_info.vapis.insert(&vapi_id, &consumer_vapi_info);
+        self.consumers.insert(&consumer_id, &consumer_info);
+        self.internal_update_storage_usage(&consumer_id, initial_storage_usage);
+    }
+
+    /// Starts the notice period for withdrawing `amount` of unused credit, restarting it for any amount already requested
+    pub fn consumer_request_withdrawal(&mut self, amount: U128) {
+        let amount: Balance = amount.into();
+        let consumer_id = env::predecessor_account_id();
+        let mut consumer_info = self.consumers.get(&consumer_id).expect("Consumer not found");
+        require!(amount > 0 && consumer_info.balance >= amount, "Consumer balance is not enough");
+
+        consumer_info.balance -= amount;
+        consumer_info.withdrawing_amount += amount;
+        consumer_info.withdrawal_start_timestamp = env::block_timestamp_ms();
+        self.consumers.insert(&consumer_id, &consumer_info);
+    }
+
+    pub fn consumer_claim_withdrawal(&mut self) -> Promise {
+        let consumer_id = env::predecessor_account_id();
+        let mut consumer_info = self.consumers.get(&consumer_id).expect("Consumer not found");
+
+        let withdrawing_amount = consumer_info.withdrawing_amount;
+        require!(withdrawing_amount > 0, "Nothing to withdraw");
+        require!(env::block_timestamp_ms() - consumer_info.withdrawal_start_timestamp >= CONSUMER_WITHDRAWAL_NOTICE_PERIOD, "Withdrawal notice period is not over");
+
+        consumer_info.withdrawing_amount = 0;
+        self.consumers.insert(&consumer_id, &consumer_info);
+
+        return ext_ft_core::ext(self.token_id.clone())
+            .with_attached_deposit(NearToken::from_yoctonear(1))
+            .with_static_gas(Gas::from_tgas(20))
+            .ft_transfer(consumer_id.clone(), U128(withdrawing_amount), None)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(Gas::from_tgas(10))
+                    .callback_consumer_claim_withdrawal(&consumer_id, withdrawing_amount)
+            );
+    }
+
+    #[private]
+    pub fn callback_consumer_claim_withdrawal(&mut self, consumer_id: &AccountId, withdrawing_amount: Balance) {
+        const WITHDRAWAL_TRANSFER_PROMISE_INDEX: u64 = 0;
+        if let PromiseResult::Failed = env::promise_result(WITHDRAWAL_TRANSFER_PROMISE_INDEX) {
+            log!("[callback_consumer_claim_withdrawal] transfer failed, consumer_id: {}", consumer_id);
+            let mut consumer_info = self.consumers.get(consumer_id).unwrap();
+            consumer_info.withdrawing_amount += withdrawing_amount;
+            self.consumers.insert(consumer_id, &consumer_info);
+        }
+    }
+
+    pub fn get_consumer_info(&self, consumer_id: &AccountId) -> Option<ConsumerInfoResponse> {
+        return self.consumers.get(consumer_id).map(|consumer_info| ConsumerInfoResponse {
+            balance: consumer_info.balance,
+            withdrawing_amount: consumer_info.withdrawing_amount,
+            withdrawal_start_timestamp: consumer_info.withdrawal_start_timestamp,
+            vapis: consumer_info.vapis.to_vec(),
+        });
+    }
+
+    pub fn get_consumer_balance(&self, consumer_id: &AccountId) -> Balance {
+        return self.consumers.get(consumer_id).map_or(0, |consumer_info| consumer_info.balance);
+    }
+
+    pub fn get_consumer_vapi_info(&self, consumer_id: &AccountId, vapi_id: String) -> Option<ConsumerVAPIInfo> {
+        return self.consumers.get(consumer_id).and_then(|consumer_info| consumer_info.vapis.get(&vapi_id));
+    }
+}
+
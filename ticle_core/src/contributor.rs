@@ -0,0 +1,164 @@
+use crate::*;
+
+const MAX_CONTRIBUTOR_COUNT: usize = 10;
+const TOTAL_SHARE_BPS: u16 = 10_000;
+
+#[near(serializers = [borsh, json])]
+pub struct ContributorInfo {
+    pub account_id: AccountId,
+    pub share_bps: u16,
+    pub unclaimed_reward_amount: Balance,
+}
+
+impl VAPIInfo {
+    /// Splits the coder revenue by the contributor shares, the coder keeps the share left and the rounding dust
+    pub(crate) fn distribute_coder_revenue(&mut self, amount: Balance) {
+        let mut contributor_amount: Balance = 0;
+        for contributor in self.contributors.iter_mut() {
+            let share_amount = amount * contributor.share_bps as u128 / TOTAL_SHARE_BPS as u128;
+            contributor.unclaimed_reward_amount += share_amount;
+            contributor_amount += share_amount;
+        }
+        self.coder_info.unclaimed_reward_amount += amount - contributor_amount;
+    }
+
+    /// Moves the coder's unclaimed rewards to a zero share contributor entry before the VAPI changes hands
+    pub(crate) fn keep_coder_rewards(&mut self) {
+        let coder_id = self.coder_info.account_id.clone();
+        let unclaimed_reward_amount = std::mem::take(&mut self.coder_info.unclaimed_reward_amount);
+        if unclaimed_reward_amount == 0 {
+            return;
+        }
+
+        match self.contributors.iter_mut().find(|contributor| contributor.account_id == coder_id) {
+            Some(contributor) => contributor.unclaimed_reward_amount += unclaimed_reward_amount,
+            None => self.contributors.push(ContributorInfo {
+                account_id: coder_id,
+                share_bps: 0,
+                unclaimed_reward_amount,
+            }),
+        }
+    }
+
+    fn reward_amount(&self, account_id: &AccountId) -> Balance {
+        let mut reward_amount = self.contributors
+            .iter()
+            .find(|contributor| contributor.account_id == *account_id)
+            .map_or(0, |contributor| contributor.unclaimed_reward_amount);
+        if self.coder_info.account_id == *account_id {
+            reward_amount += self.coder_info.unclaimed_reward_amount;
+        }
+        return reward_amount;
+    }
+
+    /// Zeroes the unclaimed rewards of `account_id` and drops the contributor entries left without share or rewards
+    pub(crate) fn take_reward(&mut self, account_id: &AccountId) -> Balance {
+        let reward_amount = self.reward_amount(account_id);
+        if self.coder_info.account_id == *account_id {
+            self.coder_info.unclaimed_reward_amount = 0;
+        }
+        if let Some(contributor) = self.contributors.iter_mut().find(|contributor| contributor.account_id == *account_id) {
+            contributor.unclaimed_reward_amount = 0;
+        }
+        self.contributors.retain(|contributor| contributor.share_bps > 0 || contributor.unclaimed_reward_amount > 0);
+        return reward_amount;
+    }
+}
+
+impl TicleCore {
+    pub(crate) fn internal_transfer_vapi_reward(&mut self, vapi_id: String, account_id: &AccountId, reward_amount: Balance) -> Promise {
+        return ext_ft_core::ext(self.token_id.clone())
+            .with_attached_deposit(NearToken::from_yoctonear(1))
+            .with_static_gas(Gas::from_tgas(20))
+            .ft_transfer(account_id.clone(), U128(reward_amount), None)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(Gas::from_tgas(20))
+                    .callback_claim_vapi_reward(vapi_id, account_id, reward_amount)
+            );
+    }
+}
+
+#[near]
+impl TicleCore {
+    /// Replaces the contributor shares of a VAPI, contributors dropped with unclaimed rewards keep them at a zero share
+    pub fn set_contributors(&mut self, vapi_id: String, account_ids: Vec<AccountId>, share_bps: Vec<u16>) {
+        let coder_id = env::predecessor_account_id();
+        let mut vapi = self.vapis.get(&vapi_id).expect("Vertical API not found");
+        require!(vapi.coder_info.account_id == coder_id, "Only coder can set contributors");
+        require!(account_ids.len() == share_bps.len(), "account_ids and share_bps must have the same length");
+        require!(share_bps.iter().map(|share_bps| *share_bps as u32).sum::<u32>() <= TOTAL_SHARE_BPS as u32, "Shares exceed 10000 basis points");
+
+        let mut contributors: Vec<ContributorInfo> = Vec::new();
+        for (account_id, share_bps) in account_ids.into_iter().zip(share_bps) {
+            require!(account_id != coder_id, "Coder keeps the share not assigned to contributors");
+            require!(contributors.iter().all(|contributor| contributor.account_id != account_id), "Duplicated contributor");
+            let unclaimed_reward_amount = vapi.contributors
+                .iter()
+                .find(|contributor| contributor.account_id == account_id)
+                .map_or(0, |contributor| contributor.unclaimed_reward_amount);
+            contributors.push(ContributorInfo { account_id, share_bps, unclaimed_reward_amount });
+        }
+        for contributor in vapi.contributors.drain(..) {
+            if contributor.unclaimed_reward_amount > 0 && contributors.iter().all(|new_contributor| new_contributor.account_id != contributor.account_id) {
+                contributors.push(ContributorInfo { share_bps: 0, ..contributor });
+            }
+        }
+        require!(contributors.len() <= MAX_CONTRIBUTOR_COUNT, "Max contributor count reached");
+
+        let initial_storage_usage = env::storage_usage();
+        vapi.contributors = contributors;
+        self.vapis.insert(&vapi_id, &vapi);
+        self.internal_update_storage_usage(&coder_id, initial_storage_usage);
+    }
+
+    /// Transfers the caller's unclaimed revenue of a VAPI, as coder and as contributor
+    pub fn claim_vapi_reward(&mut self, vapi_id: String) -> Promise {
+        let account_id = env::predecessor_account_id();
+        let mut vapi = self.vapis.get(&vapi_id).expect("Vertical API not found");
+
+        let reward_amount = vapi.take_reward(&account_id);
+        require!(reward_amount > 0, "Nothing to claim");
+
+        // Storage of a dropped contributor is released to the coder who paid for it
+        let initial_storage_usage = env::storage_usage();
+        self.vapis.insert(&vapi_id, &vapi);
+        self.internal_update_storage_usage(&vapi.coder_info.account_id, initial_storage_usage);
+
+        log!("[claim_vapi_reward] vapi_id: {}, account_id: {}, amount: {}", vapi_id, account_id, reward_amount);
+        return self.internal_transfer_vapi_reward(vapi_id, &account_id, reward_amount);
+    }
+
+    /// A failed transfer is restored to the coder rewards if the caller is still the coder, otherwise to a zero share contributor
+    #[private]
+    pub fn callback_claim_vapi_reward(&mut self, vapi_id: String, account_id: &AccountId, reward_amount: Balance) {
+        const REWARD_TRANSFER_PROMISE_INDEX: u64 = 0;
+        if let PromiseResult::Successful(_) = env::promise_result(REWARD_TRANSFER_PROMISE_INDEX) {
+            return;
+        }
+
+        log!("[callback_claim_vapi_reward] transfer failed, vapi_id: {}, account_id: {}", vapi_id, account_id);
+        let mut vapi = self.vapis.get(&vapi_id).unwrap();
+        if vapi.coder_info.account_id == *account_id {
+            vapi.coder_info.unclaimed_reward_amount += reward_amount;
+        } else if let Some(contributor) = vapi.contributors.iter_mut().find(|contributor| contributor.account_id == *account_id) {
+            contributor.unclaimed_reward_amount += reward_amount;
+        } else {
+            vapi.contributors.push(ContributorInfo {
+                account_id: account_id.clone(),
+                share_bps: 0,
+                unclaimed_reward_amount: reward_amount,
+            });
+        }
+        self.vapis.insert(&vapi_id, &vapi);
+    }
+
+    pub fn get_vapi_contributors(&self, vapi_id: String) -> Vec<ContributorInfo> {
+        return self.vapis.get(&vapi_id).map_or(Vec::new(), |vapi| vapi.contributors);
+    }
+
+    pub fn get_vapi_reward_amount(&self, vapi_id: String, account_id: &AccountId) -> Balance {
+        return self.vapis.get(&vapi_id).map_or(0, |vapi| vapi.reward_amount(account_id));
+    }
+}
+
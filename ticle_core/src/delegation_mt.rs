@@ -0,0 +1,268 @@
+use near_sdk::assert_one_yocto;
+
+use crate::*;
+
+const GAS_FOR_RESOLVE_TRANSFER: Gas = Gas::from_tgas(30);
+const GAS_FOR_MT_TRANSFER_CALL: Gas = Gas::from_tgas(60);
+
+const MT_STANDARD_NAME: &str = "nep245";
+const MT_METADATA_SPEC: &str = "1.0.0";
+
+/// `(approved account, approval id, approved amount)` of the owners, approvals are not supported so it's always `None`
+pub type ResolverApprovals = Option<Vec<Option<Vec<(AccountId, u64, U128)>>>>;
+
+#[ext_contract(ext_mt_receiver)]
+pub trait MultiTokenReceiver {
+    fn mt_on_transfer(&mut self, sender_id: AccountId, previous_owner_ids: Vec<AccountId>, token_ids: Vec<AccountId>, amounts: Vec<U128>, msg: String) -> PromiseOrValue<Vec<U128>>;
+}
+
+/// Delegations are fungible within a reviewer, so tokens have no single owner
+#[near(serializers = [json])]
+pub struct Token {
+    pub token_id: AccountId,
+    pub owner_id: Option<AccountId>,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct MtMintLog<'a> {
+    owner_id: &'a AccountId,
+    token_ids: &'a [&'a AccountId],
+    amounts: &'a [U128],
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct MtBurnLog<'a> {
+    owner_id: &'a AccountId,
+    token_ids: &'a [&'a AccountId],
+    amounts: &'a [U128],
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct MtTransferLog<'a> {
+    old_owner_id: &'a AccountId,
+    new_owner_id: &'a AccountId,
+    token_ids: &'a [AccountId],
+    amounts: &'a [U128],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    memo: Option<&'a str>,
+}
+
+fn emit_mt_event<T: Serialize>(event: &str, data: T) {
+    let event = serde_json::json!({
+        "standard": MT_STANDARD_NAME,
+        "version": MT_METADATA_SPEC,
+        "event": event,
+        "data": [data],
+    });
+    env::log_str(&format!("EVENT_JSON:{}", event));
+}
+
+pub(crate) fn emit_mt_mint(owner_id: &AccountId, reviewer_id: &AccountId, amount: Balance) {
+    emit_mt_event("mt_mint", MtMintLog { owner_id, token_ids: &[reviewer_id], amounts: &[U128(amount)] });
+}
+
+pub(crate) fn emit_mt_burn(owner_id: &AccountId, reviewer_id: &AccountId, amount: Balance) {
+    emit_mt_event("mt_burn", MtBurnLog { owner_id, token_ids: &[reviewer_id], amounts: &[U128(amount)] });
+}
+
+fn emit_mt_transfer(old_owner_id: &AccountId, new_owner_id: &AccountId, token_ids: &[AccountId], amounts: &[U128], memo: Option<&str>) {
+    emit_mt_event("mt_transfer", MtTransferLog { old_owner_id, new_owner_id, token_ids, amounts, memo });
+}
+
+impl TicleCore {
+    /// Moves the pending reward of a delegator into its deposit, as a new deposit to the reviewer does
+    fn settle_delegator_reward(&self, reviewer_info: &mut ReviewerInfo, delegator_info: &mut DelegatorInfo) {
+        let reward = self.pending_reward(delegator_info.deposit_info.deposit_amount, delegator_info.deposit_info.reward_debt, reviewer_info.acc_reward_per_share);
+        delegator_info.deposit_info.deposit_amount += reward;
+        delegator_info.deposit_info.reward_debt = accumulated_reward(delegator_info.deposit_info.deposit_amount, reviewer_info.acc_reward_per_share);
+        reviewer_info.total_delegator_deposit_amount += reward;
+    }
+
+    /// Deposit and pending reward of a delegation, its refunding amount is not part of the balance
+    fn internal_delegation_balance(&self, account_id: &AccountId, reviewer_id: &AccountId) -> Balance {
+        let Some(reviewer_info) = self.reviewers.get(reviewer_id) else {
+            return 0;
+        };
+        return reviewer_info.delegators.get(account_id).map_or(0, |delegator_info| {
+            delegator_info.deposit_info.deposit_amount + self.pending_reward(delegator_info.deposit_info.deposit_amount, delegator_info.deposit_info.reward_debt, reviewer_info.acc_reward_per_share)
+        });
+    }
+
+    /// Moves `amount` of the delegation to `reviewer_id` from `sender_id` to `receiver_id`. The pending rewards of
+    /// both are settled first, so the reward debt moves along with the amount. An empty sender record is removed.
+    /// A new record of the receiver is charged to its storage deposit, so the receiver has to be registered.
+    /// Transfers can't go to a deregistering reviewer or into its liquid pool. A refund of `mt_transfer_call`
+    /// recreates the record of the previous owner and never fails on storage, a storage deposit drained meanwhile
+    /// is covered by the contract.
+    /// A storage error of a transfer is returned after the move, the caller panics on it.
+    fn internal_move_delegation(&mut self, reviewer_id: &AccountId, sender_id: &AccountId, receiver_id: &AccountId, amount: Balance, is_refund: bool) -> Result<(), String> {
+        if sender_id == receiver_id {
+            return Err("Sender and receiver must be different".to_string());
+        }
+        if amount == 0 {
+            return Err("The amount should be a positive number".to_string());
+        }
+        let mut reviewer_info = self.reviewers.get(reviewer_id).ok_or("Reviewer not found")?;
+        if !is_refund && reviewer_info.exit_timestamp.is_some() {
+            return Err("Reviewer is deregistering".to_string());
+        }
+        // The delegation of a liquid pool backs its shares, a transfer into it would change their price
+        if !is_refund && self.liquid_pools.get(reviewer_id).is_some_and(|pool| pool.token_id == *receiver_id) {
+            return Err("Delegations can't be transferred to the liquid pool".to_string());
+        }
+        let mut sender_info = reviewer_info.delegators.get(sender_id).ok_or("Delegator not found")?;
+        self.settle_delegator_reward(&mut reviewer_info, &mut sender_info);
+        if sender_info.deposit_info.deposit_amount < amount {
+            return Err("Delegator balance is less than the amount to transfer".to_string());
+        }
+
+        if !is_refund && !self.accounts.contains_key(receiver_id) {
+            return Err(format!("The account {} is not registered", receiver_id));
+        }
+        let mut receiver_info = reviewer_info.delegators.get(receiver_id).unwrap_or(DelegatorInfo {
+            deposit_info: DepositInfo {
+                deposit_amount: 0,
+                reward_debt: 0,
+            },
+            refunding_amount: 0,
+            refunding_start_timestamp: 0,
+        });
+        self.settle_delegator_reward(&mut reviewer_info, &mut receiver_info);
+
+        sender_info.deposit_info.deposit_amount -= amount;
+        sender_info.deposit_info.reward_debt = accumulated_reward(sender_info.deposit_info.deposit_amount, reviewer_info.acc_reward_per_share);
+        receiver_info.deposit_info.deposit_amount += amount;
+        receiver_info.deposit_info.reward_debt = accumulated_reward(receiver_info.deposit_info.deposit_amount, reviewer_info.acc_reward_per_share);
+
+        let initial_storage_usage = env::storage_usage();
+        if sender_info.deposit_info.deposit_amount == 0 && sender_info.refunding_amount == 0 {
+            reviewer_info.delegators.remove(sender_id);
+        } else {
+            reviewer_info.delegators.insert(sender_id, &sender_info);
+        }
+        let sender_result = self.internal_try_update_storage_usage(sender_id, initial_storage_usage);
+
+        let initial_storage_usage = env::storage_usage();
+        reviewer_info.delegators.insert(receiver_id, &receiver_info);
+        let receiver_result = self.internal_try_update_storage_usage(receiver_id, initial_storage_usage);
+
+        self.reviewers.insert(reviewer_id, &reviewer_info);
+        let result = sender_result.and(receiver_result);
+        if let (true, Err(err)) = (is_refund, &result) {
+            log!("[internal_move_delegation] {}", err);
+            return Ok(());
+        }
+        return result;
+    }
+
+    fn internal_mt_batch_transfer(&mut self, sender_id: &AccountId, receiver_id: &AccountId, token_ids: &[AccountId], amounts: &[U128], approvals: Option<Vec<Option<(AccountId, u64)>>>, memo: Option<String>) {
+        require!(!token_ids.is_empty(), "token_ids must not be empty");
+        require!(token_ids.len() == amounts.len(), "token_ids and amounts must have the same length");
+        require!(approvals.is_none_or(|approvals| approvals.iter().all(|approval| approval.is_none())), "Approvals are not supported");
+
+        for (reviewer_id, amount) in token_ids.iter().zip(amounts.iter()) {
+            self.internal_move_delegation(reviewer_id, sender_id, receiver_id, amount.0, false).unwrap_or_else(|err| env::panic_str(&err));
+        }
+        emit_mt_transfer(sender_id, receiver_id, token_ids, amounts, memo.as_deref());
+    }
+}
+
+#[near]
+impl TicleCore {
+    /// Transfers a delegation position, the token id is the reviewer id
+    #[payable]
+    pub fn mt_transfer(&mut self, receiver_id: AccountId, token_id: AccountId, amount: U128, approval: Option<(AccountId, u64)>, memo: Option<String>) {
+        assert_one_yocto();
+        self.internal_mt_batch_transfer(&env::predecessor_account_id(), &receiver_id, &[token_id], &[amount], Some(vec![approval]), memo);
+    }
+
+    #[payable]
+    pub fn mt_batch_transfer(&mut self, receiver_id: AccountId, token_ids: Vec<AccountId>, amounts: Vec<U128>, approvals: Option<Vec<Option<(AccountId, u64)>>>, memo: Option<String>) {
+        assert_one_yocto();
+        self.internal_mt_batch_transfer(&env::predecessor_account_id(), &receiver_id, &token_ids, &amounts, approvals, memo);
+    }
+
+    #[payable]
+    pub fn mt_transfer_call(&mut self, receiver_id: AccountId, token_id: AccountId, amount: U128, approval: Option<(AccountId, u64)>, memo: Option<String>, msg: String) -> PromiseOrValue<Vec<U128>> {
+        return self.mt_batch_transfer_call(receiver_id, vec![token_id], vec![amount], Some(vec![approval]), memo, msg);
+    }
+
+    /// Transfers the positions and lets the receiver return unused amounts, which are moved back to the sender
+    #[payable]
+    pub fn mt_batch_transfer_call(&mut self, receiver_id: AccountId, token_ids: Vec<AccountId>, amounts: Vec<U128>, approvals: Option<Vec<Option<(AccountId, u64)>>>, memo: Option<String>, msg: String) -> PromiseOrValue<Vec<U128>> {
+        assert_one_yocto();
+        require!(env::prepaid_gas() > GAS_FOR_MT_TRANSFER_CALL, "More gas is required");
+        let sender_id = env::predecessor_account_id();
+        self.internal_mt_batch_transfer(&sender_id, &receiver_id, &token_ids, &amounts, approvals, memo);
+
+        let previous_owner_ids = vec![sender_id.clone(); token_ids.len()];
+        return ext_mt_receiver::ext(receiver_id.clone())
+            .with_static_gas(env::prepaid_gas().saturating_sub(GAS_FOR_MT_TRANSFER_CALL))
+            .mt_on_transfer(sender_id, previous_owner_ids.clone(), token_ids.clone(), amounts.clone(), msg)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_RESOLVE_TRANSFER)
+                    .mt_resolve_transfer(previous_owner_ids, receiver_id, token_ids, amounts, None)
+            )
+            .into();
+    }
+
+    /// Returns the used amounts. Unused amounts are moved back as far as the receiver still holds them,
+    /// a failed receiver call leaves every amount unused. An amount that can't be moved back counts as used.
+    #[private]
+    #[allow(unused_variables)]
+    pub fn mt_resolve_transfer(&mut self, previous_owner_ids: Vec<AccountId>, receiver_id: AccountId, token_ids: Vec<AccountId>, amounts: Vec<U128>, approvals: ResolverApprovals) -> Vec<U128> {
+        const RECEIVER_PROMISE_INDEX: u64 = 0;
+        let unused_amounts = match env::promise_result(RECEIVER_PROMISE_INDEX) {
+            PromiseResult::Successful(value) => serde_json::from_slice::<Vec<U128>>(&value)
+                .ok()
+                .filter(|unused_amounts| unused_amounts.len() == amounts.len())
+                .unwrap_or_else(|| amounts.clone()),
+            PromiseResult::Failed => amounts.clone(),
+        };
+
+        let mut used_amounts = Vec::with_capacity(amounts.len());
+        for (i, reviewer_id) in token_ids.iter().enumerate() {
+            let unused_amount = unused_amounts[i].0.min(amounts[i].0);
+            let mut refund_amount = unused_amount.min(self.internal_delegation_balance(&receiver_id, reviewer_id));
+            if refund_amount > 0 {
+                match self.internal_move_delegation(reviewer_id, &receiver_id, &previous_owner_ids[i], refund_amount, true) {
+                    Ok(()) => emit_mt_transfer(&receiver_id, &previous_owner_ids[i], &token_ids[i..=i], &[U128(refund_amount)], Some("refund")),
+                    Err(err) => {
+                        log!("[mt_resolve_transfer] {}", err);
+                        refund_amount = 0;
+                    }
+                }
+            }
+            used_amounts.push(U128(amounts[i].0 - refund_amount));
+        }
+        return used_amounts;
+    }
+
+    pub fn mt_token(&self, token_ids: Vec<AccountId>) -> Vec<Option<Token>> {
+        return token_ids
+            .into_iter()
+            .map(|token_id| self.reviewers.contains_key(&token_id).then_some(Token { token_id, owner_id: None }))
+            .collect();
+    }
+
+    pub fn mt_balance_of(&self, account_id: AccountId, token_id: AccountId) -> U128 {
+        return U128(self.internal_delegation_balance(&account_id, &token_id));
+    }
+
+    pub fn mt_batch_balance_of(&self, account_id: AccountId, token_ids: Vec<AccountId>) -> Vec<U128> {
+        return token_ids.iter().map(|token_id| U128(self.internal_delegation_balance(&account_id, token_id))).collect();
+    }
+
+    /// Delegated amount of a reviewer, pending rewards of the delegators are counted once settled
+    pub fn mt_supply(&self, token_id: AccountId) -> Option<U128> {
+        return self.reviewers.get(&token_id).map(|reviewer_info| U128(reviewer_info.total_delegator_deposit_amount));
+    }
+
+    pub fn mt_batch_supply(&self, token_ids: Vec<AccountId>) -> Vec<Option<U128>> {
+        return token_ids.into_iter().map(|token_id| self.mt_supply(token_id)).collect();
+    }
+}
@@ -2,11 +2,54 @@ use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
 
 use crate::*;
 
+const TOKEN_RECEIVER_MESSAGE_VERSION: u8 = 1;
+
+fn default_message_version() -> u8 {
+    return TOKEN_RECEIVER_MESSAGE_VERSION;
+}
+
+/// `{"action": "...", "version": 1, ...}`
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TokenReceiverMessage {
+    #[serde(default = "default_message_version")]
+    pub version: u8,
+    #[serde(flatten)]
+    pub action: TokenReceiverAction,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum TokenReceiverAction {
+    Settlement {
+        settlement_id: u64,
+        vapi_ids: Vec<String>,
+        amounts: Vec<U128>,
+    },
+    DepositToReviewer {
+        reviewer_id: AccountId,
+    },
+    /// Delegates through the reviewer's liquid pool, the sender receives liquid shares instead of a delegation
+    StakeLiquid {
+        reviewer_id: AccountId,
+    },
+    /// Prefunds the sender's consumer balance, which usage vouchers are redeemed against
+    FundConsumer,
+    /// Pays one or more periods of a subscription plan, renewing the sender's subscription if it exists
+    Subscribe {
+        plan_id: u64,
+    },
+}
+
+/// Message shapes accepted before the `action` tag was introduced
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
 #[serde(untagged)]
-enum TokenReceiverMessage {
+enum LegacyTokenReceiverMessage {
+    /// Settlements are deduplicated by `settlement_id` now, the old shape without it is rejected with a hint
     Settlement {
+        settlement_id: Option<u64>,
         vapi_ids: Vec<String>,
         amounts: Vec<U128>,
     },
@@ -15,8 +58,40 @@ enum TokenReceiverMessage {
     },
 }
 
+impl TryFrom<LegacyTokenReceiverMessage> for TokenReceiverAction {
+    type Error = String;
+
+    fn try_from(message: LegacyTokenReceiverMessage) -> Result<Self, Self::Error> {
+        match message {
+            LegacyTokenReceiverMessage::Settlement { settlement_id: Some(settlement_id), vapi_ids, amounts } => Ok(TokenReceiverAction::Settlement { settlement_id, vapi_ids, amounts }),
+            LegacyTokenReceiverMessage::Settlement { settlement_id: None, .. } => Err(
+                "Settlement requires a settlement_id, use {\"action\": \"settlement\", \"settlement_id\": ..., \"vapi_ids\": [...], \"amounts\": [...]}".to_string()
+            ),
+            LegacyTokenReceiverMessage::DepositToReviewer { reviewer_id } => Ok(TokenReceiverAction::DepositToReviewer { reviewer_id }),
+        }
+    }
+}
+
+impl TokenReceiverMessage {
+    pub fn parse(msg: &str) -> Result<TokenReceiverAction, String> {
+        let value = serde_json::from_str::<serde_json::Value>(msg).map_err(|err| err.to_string())?;
+        if value.get("action").is_none() {
+            let message = serde_json::from_value::<LegacyTokenReceiverMessage>(value).map_err(|err| err.to_string())?;
+            return message.try_into();
+        }
+
+        let message = serde_json::from_value::<TokenReceiverMessage>(value).map_err(|err| err.to_string())?;
+        if message.version != TOKEN_RECEIVER_MESSAGE_VERSION {
+            return Err(format!("Unsupported message version: {}", message.version));
+        }
+        return Ok(message.action);
+    }
+}
+
 #[near]
 impl FungibleTokenReceiver for TicleCore {
+    /// Returns the whole `amount` as unused when the message is invalid or the action fails,
+    /// so that `ft_resolve_transfer` refunds the sender.
     fn ft_on_transfer(&mut self, sender_id: AccountId, amount: U128, msg: String) -> PromiseOrValue<U128> {
         let token_id: AccountId = env::predecessor_account_id();
         require!(token_id == self.token_id, "Invalid token");
@@ -27,18 +102,38 @@ impl FungibleTokenReceiver for TicleCore {
 
         log!("[ft_on_transfer] sender_id: {}", sender_id);
         log!("[ft_on_transfer] msg: {}", msg);
-        let message = serde_json::from_str::<TokenReceiverMessage>(&msg).expect("Invalid message format");
+        let action = match TokenReceiverMessage::parse(&msg) {
+            Ok(action) => action,
+            Err(err) => {
+                log!("[ft_on_transfer] invalid message: {}", err);
+                return PromiseOrValue::Value(amount);
+            }
+        };
         log!("[ft_on_transfer] selected message");
 
-        match message {
-            TokenReceiverMessage::DepositToReviewer { reviewer_id } => {
-                self.internal_deposit_to_reviewer(&sender_id, &reviewer_id, amount.into());
+        let result = match action {
+            TokenReceiverAction::DepositToReviewer { reviewer_id } => {
+                self.internal_deposit_to_reviewer(&sender_id, &reviewer_id, amount.into()).map(|_| ())
             }
-            TokenReceiverMessage::Settlement { vapi_ids, amounts } => {
-                self.internal_settlement(&sender_id, vapi_ids, amounts);
+            TokenReceiverAction::StakeLiquid { reviewer_id } => {
+                self.internal_stake_liquid(&sender_id, &reviewer_id, amount.into()).map(|_| ())
             }
+            TokenReceiverAction::Settlement { settlement_id, vapi_ids, amounts } => {
+                self.internal_settlement(&sender_id, settlement_id, vapi_ids, amounts, amount.into()).map(|_| ())
+            }
+            TokenReceiverAction::FundConsumer => {
+                self.internal_fund_consumer(&sender_id, amount.into())
+            }
+            TokenReceiverAction::Subscribe { plan_id } => {
+                self.internal_subscribe(&sender_id, plan_id, amount.into())
+            }
+        };
+
+        if let Err(err) = result {
+            log!("[ft_on_transfer] failed: {}", err);
+            return PromiseOrValue::Value(amount);
         }
 
         return PromiseOrValue::Value(U128(0));
     }
-}
\ No newline at end of file
+}
@@ -1,11 +1,41 @@
+#![allow(clippy::needless_return)]
+
 use near_contract_standards::fungible_token::Balance;
 use near_contract_standards::fungible_token::core::ext_ft_core;
 use near_sdk::{AccountId, env, ext_contract, Gas, log, near, NearToken, PanicOnDefault, Promise, PromiseOrValue, PromiseResult, require, serde_json};
-use near_sdk::collections::{LookupMap, UnorderedMap};
+use near_contract_standards::non_fungible_token::metadata::NFTContractMetadata;
+use near_contract_standards::non_fungible_token::NonFungibleToken;
+use near_sdk::collections::{LazyOption, LookupMap, UnorderedMap};
 use near_sdk::json_types::U128;
 use near_sdk::serde::{Deserialize, Serialize};
+use ticle_math::mul_div;
 
+pub mod consumer;
+pub mod contributor;
+pub mod delegation_mt;
 pub mod ft_receiver;
+pub mod liquid;
+pub mod ownership;
+pub mod reviewer_exit;
+pub mod signer;
+pub mod storage;
+pub mod subscription;
+pub mod vapi_nft;
+
+/// Time a delegator refund unbonds before it can be claimed, in milliseconds
+const REFUNDING_PERIOD: u64 = 60 * 1_000;
+/// Scale of `acc_reward_per_share`
+const ACC_REWARD_PRECISION: Balance = 1_000_000_000_000;
+
+/// Rewards accumulated by `deposit_amount` at `acc_reward_per_share`, the product overflows u128 for large deposits
+fn accumulated_reward(deposit_amount: Balance, acc_reward_per_share: Balance) -> Balance {
+    return mul_div(deposit_amount, acc_reward_per_share, ACC_REWARD_PRECISION).expect("Reward amount overflow");
+}
+
+/// Increase of `acc_reward_per_share` when `reward_amount` is shared by `total_deposit_amount`
+fn reward_per_share(reward_amount: Balance, total_deposit_amount: Balance) -> Balance {
+    return mul_div(reward_amount, ACC_REWARD_PRECISION, total_deposit_amount).expect("Reward amount overflow");
+}
 
 #[near(contract_state)]
 #[derive(PanicOnDefault)]
@@ -14,9 +44,25 @@ pub struct TicleCore {
     reviewers: LookupMap<AccountId, ReviewerInfo>,
     token_id: AccountId,
     owner_id: AccountId,
-    signer_public_key: Vec<u8>,
+    signers: UnorderedMap<Vec<u8>, signer::SignerKey>,
+    signer_threshold: u8,
+    high_value_amount: Balance,
     treasury: Balance,
     max_depositable_vapi_count: u8,
+    accounts: LookupMap<AccountId, storage::AccountStorage>,
+    account_storage_usage: u64,
+    settlements: LookupMap<u64, SettlementReceipt>,
+    pending_burn_amount: Balance,
+    total_burned_amount: Balance,
+    consumers: LookupMap<AccountId, consumer::ConsumerInfo>,
+    subscription_plans: LookupMap<u64, subscription::SubscriptionPlan>,
+    subscription_plan_count: u64,
+    subscriptions: LookupMap<(AccountId, u64), subscription::Subscription>,
+    vapi_tokens: NonFungibleToken,
+    vapi_token_metadata: LazyOption<NFTContractMetadata>,
+    liquid_pools: LookupMap<AccountId, liquid::LiquidPool>,
+    /// Refunds of removed reviewers' delegators and failed refunds of cancelled subscriptions, claimable with `claim_exit_refund`
+    exit_refunds: LookupMap<AccountId, Balance>,
 }
 
 #[near(serializers = [borsh])]
@@ -24,6 +70,10 @@ pub struct VAPIInfo {
     coder_info: CoderInfo,
     total_deposit_amount: Balance,
     acc_reward_per_share: Balance,
+    contributors: Vec<contributor::ContributorInfo>,
+    pending_transfer: Option<ownership::OwnershipTransfer>,
+    /// Storage of the VAPI token records measured at mint, charged to whoever is the coder
+    token_storage_usage: u64,
 }
 
 #[near(serializers = [borsh])]
@@ -40,6 +90,10 @@ pub struct ReviewerInfo {
     delegators: UnorderedMap<AccountId, DelegatorInfo>,
     total_delegator_deposit_amount: Balance,
     acc_reward_per_share: Balance,
+    /// Set once the reviewer starts deregistering, no new delegations are accepted from then on
+    exit_timestamp: Option<u64>,
+    /// Delegators at indices below the cursor are still to be visited by `unbond_reviewer_delegators`
+    unbond_cursor: u64,
 }
 
 #[near(serializers = [borsh])]
@@ -61,6 +115,26 @@ pub struct GetDepositInfoResponse {
     pub reward: Balance,
 }
 
+/// Totals distributed by a settlement, `amounts` are per VAPI in the order of `vapi_ids`.
+/// `treasury_amount` includes `surplus_amount`, the part of the transfer not covered by `amounts`.
+#[near(serializers = [borsh, json])]
+pub struct SettlementReceipt {
+    pub vapi_ids: Vec<String>,
+    pub amounts: Vec<Balance>,
+    pub reviewer_fee_amount: Balance,
+    pub burn_amount: Balance,
+    pub treasury_amount: Balance,
+    pub surplus_amount: Balance,
+    pub timestamp: u64,
+}
+
+/// Totals of `internal_distribute`
+struct Distribution {
+    reviewer_fee_amount: Balance,
+    burn_amount: Balance,
+    treasury_amount: Balance,
+}
+
 #[ext_contract(ext_ft_burn)]
 pub trait FungibleTokenBurn {
     fn burn(&mut self, amount: U128);
@@ -76,22 +150,47 @@ impl TicleCore {
         // It will not be used, so remove it
         signer_public_key.remove(0);
 
-        Self {
+        let (vapi_tokens, vapi_token_metadata) = vapi_nft::new_vapi_tokens(owner_id.clone());
+        let mut this = Self {
             vapis: LookupMap::new(b"v".to_vec()),
             reviewers: LookupMap::new(b"r".to_vec()),
             token_id,
             owner_id,
-            signer_public_key,
+            signers: signer::new_signers(signer_public_key),
+            signer_threshold: 1,
+            high_value_amount: Balance::MAX,
             treasury: 0,
             max_depositable_vapi_count: 10,
-        }
+            accounts: LookupMap::new(b"a".to_vec()),
+            account_storage_usage: 0,
+            settlements: LookupMap::new(b"s".to_vec()),
+            pending_burn_amount: 0,
+            total_burned_amount: 0,
+            consumers: LookupMap::new(b"c".to_vec()),
+            subscription_plans: LookupMap::new(b"p".to_vec()),
+            subscription_plan_count: 0,
+            subscriptions: LookupMap::new(b"u".to_vec()),
+            vapi_tokens,
+            vapi_token_metadata,
+            liquid_pools: LookupMap::new(b"l".to_vec()),
+            exit_refunds: LookupMap::new(b"x".to_vec()),
+        };
+        this.measure_account_storage_usage();
+
+        return this;
     }
 }
 
 #[near]
 impl TicleCore {
+    /// Most recently added active signer key
     pub fn get_signer_public_key(&self) -> Vec<u8> {
-        return self.signer_public_key.clone();
+        return self.signers
+            .iter()
+            .filter(|(_, signer)| signer.is_active())
+            .max_by_key(|(_, signer)| signer.added_at)
+            .map(|(public_key, _)| public_key)
+            .unwrap_or_default();
     }
 
     pub fn get_reviewer_deposit_info(&self, reviewer_id: &AccountId, vapi_id: String) -> GetDepositInfoResponse {
@@ -100,7 +199,7 @@ impl TicleCore {
             reward: 0,
         };
 
-        let reviewer_info = self.reviewers.get(&reviewer_id);
+        let reviewer_info = self.reviewers.get(reviewer_id);
         if reviewer_info.is_none() {
             return result;
         }
@@ -123,13 +222,13 @@ impl TicleCore {
             reward: 0,
         };
 
-        let reviewer_info = self.reviewers.get(&reviewer_id);
+        let reviewer_info = self.reviewers.get(reviewer_id);
         if reviewer_info.is_none() {
             return result;
         }
 
         let reviewer_info = reviewer_info.unwrap();
-        let delegator_info = reviewer_info.delegators.get(&delegator_id);
+        let delegator_info = reviewer_info.delegators.get(delegator_id);
         if delegator_info.is_none() {
             return result;
         }
@@ -141,46 +240,83 @@ impl TicleCore {
     }
 
     pub fn get_reviewer_pending_amount(&self, reviewer_id: &AccountId) -> Balance {
-        let reviewer_info = self.reviewers.get(&reviewer_id).expect("Reviewer not found");
+        let reviewer_info = self.reviewers.get(reviewer_id).expect("Reviewer not found");
         return reviewer_info.pending_amount;
     }
 
     pub fn get_reviewer_royalty_amount(&self, reviewer_id: &AccountId) -> Balance {
-        let reviewer_info = self.reviewers.get(&reviewer_id).expect("Reviewer not found");
+        let reviewer_info = self.reviewers.get(reviewer_id).expect("Reviewer not found");
         return reviewer_info.royalty_amount;
     }
+
+    pub fn get_settlement_receipt(&self, settlement_id: u64) -> Option<SettlementReceipt> {
+        return self.settlements.get(&settlement_id);
+    }
+
+    pub fn get_total_burned_amount(&self) -> Balance {
+        return self.total_burned_amount;
+    }
+
+    pub fn get_pending_burn_amount(&self) -> Balance {
+        return self.pending_burn_amount;
+    }
 }
 
 #[near]
 impl TicleCore {
+    fn assert_owner(&self) {
+        require!(env::predecessor_account_id() == self.owner_id, "Only owner can call this method");
+    }
+
     fn pending_reward(&self, deposit_amount: Balance, reward_debt: Balance, acc_reward_per_share: Balance) -> Balance {
-        let new_reward_debt = deposit_amount * acc_reward_per_share / 1_000_000_000_000;
+        let new_reward_debt = accumulated_reward(deposit_amount, acc_reward_per_share);
         return new_reward_debt - reward_debt;
     }
 
     pub fn create_vapi(&mut self, vapi_id: String) {
         let coder_id = env::predecessor_account_id();
+        self.assert_registered(&coder_id);
+        require!(!self.vapis.contains_key(&vapi_id), "VAPI already exists");
+
+        let initial_storage_usage = env::storage_usage();
+        let token_storage_usage = self.internal_mint_vapi_token(&vapi_id, &coder_id);
         let vapi = VAPIInfo {
             coder_info: CoderInfo {
-                account_id: coder_id,
+                account_id: coder_id.clone(),
                 unclaimed_reward_amount: 0,
             },
             total_deposit_amount: 0,
             acc_reward_per_share: 0,
+            contributors: Vec::new(),
+            pending_transfer: None,
+            token_storage_usage,
         };
         self.vapis.insert(&vapi_id, &vapi);
+        self.internal_update_storage_usage(&coder_id, initial_storage_usage);
     }
 
+    /// Registers the caller as a reviewer, the reviewer record is charged to its own storage balance
     pub fn create_reviewer(&mut self, reviewer_id: &AccountId) {
+        require!(env::predecessor_account_id() == *reviewer_id, "Only the reviewer can create itself");
+        self.assert_registered(reviewer_id);
+        require!(!self.reviewers.contains_key(reviewer_id), "Reviewer already exists");
+
+        // Nested collections need a prefix unique to the reviewer, otherwise every reviewer shares the same entries
+        let reviewer_prefix = env::sha256(reviewer_id.as_bytes());
+
+        let initial_storage_usage = env::storage_usage();
         let reviewer = ReviewerInfo {
-            deposit_vapis: UnorderedMap::new(b"dv".to_vec()),
+            deposit_vapis: UnorderedMap::new([b"dv".as_slice(), &reviewer_prefix].concat()),
             total_delegator_deposit_amount: 0,
             pending_amount: 0,
             royalty_amount: 0,
-            delegators: UnorderedMap::new(b"d".to_vec()),
+            delegators: UnorderedMap::new([b"d".as_slice(), &reviewer_prefix].concat()),
             acc_reward_per_share: 0,
+            exit_timestamp: None,
+            unbond_cursor: 0,
         };
-        self.reviewers.insert(&reviewer_id, &reviewer);
+        self.reviewers.insert(reviewer_id, &reviewer);
+        self.internal_update_storage_usage(reviewer_id, initial_storage_usage);
     }
 
     #[payable]
@@ -189,9 +325,11 @@ impl TicleCore {
         log!("[deposit_to_vapi] vapi_id: {}, amount: {}", vapi_id, amount);
 
         let reviewer_id = env::predecessor_account_id();
+        let initial_storage_usage = env::storage_usage();
 
         let mut vapi = self.vapis.get(&vapi_id).expect("Vertical API not found");
         let mut reviewer_info = self.reviewers.get(&reviewer_id).expect("Reviewer not found");
+        self.assert_reviewer_active(&reviewer_info);
         require!(reviewer_info.pending_amount >= amount, "pending amount must be greater than amount");
 
         let mut deposit_info = reviewer_info.deposit_vapis.get(&vapi_id).unwrap_or(DepositInfo {
@@ -202,7 +340,7 @@ impl TicleCore {
         let reward = self.pending_reward(deposit_info.deposit_amount, deposit_info.reward_debt, vapi.acc_reward_per_share);
         
         deposit_info.deposit_amount += amount + reward;
-        deposit_info.reward_debt = deposit_info.deposit_amount * vapi.acc_reward_per_share / 1_000_000_000_000;
+        deposit_info.reward_debt = accumulated_reward(deposit_info.deposit_amount, vapi.acc_reward_per_share);
 
         reviewer_info.pending_amount -= amount;
         reviewer_info.deposit_vapis.insert(&vapi_id, &deposit_info);
@@ -213,6 +351,7 @@ impl TicleCore {
 
         vapi.total_deposit_amount += amount;
         self.vapis.insert(&vapi_id, &vapi);
+        self.internal_update_storage_usage(&reviewer_id, initial_storage_usage);
 
         // TODO: should emit success event
         return Promise::new(reviewer_id.clone());
@@ -231,32 +370,22 @@ impl TicleCore {
                 continue;
             }
 
-            let royalty_amount = reward * 1 / 100;
+            let royalty_amount = reward / 100;
             total_royalty_amount += royalty_amount;
 
             let delegator_reward_amount = reward - royalty_amount;
             total_delegator_reward_amount += delegator_reward_amount;
 
             deposit_info.deposit_amount += delegator_reward_amount;
-            deposit_info.reward_debt = deposit_info.deposit_amount * vapi.acc_reward_per_share / 1_000_000_000_000;
+            deposit_info.reward_debt = accumulated_reward(deposit_info.deposit_amount, vapi.acc_reward_per_share);
             reviewer_info.deposit_vapis.insert(&vapi_id, &deposit_info);
             vapi.total_deposit_amount += delegator_reward_amount;
             self.vapis.insert(&vapi_id, &vapi);
         }
 
         reviewer_info.royalty_amount += total_royalty_amount;
-        reviewer_info.acc_reward_per_share += total_delegator_reward_amount * 1_000_000_000_000 / reviewer_info.total_delegator_deposit_amount;
-        self.reviewers.insert(&reviewer_id, &reviewer_info);
-    }
-
-    pub fn transfer_ownership(&mut self, vapi_id: String, new_coder_id: AccountId) {
-        let mut vapi = self.vapis.get(&vapi_id).expect("Vertical API not found");
-
-        let account_id = env::predecessor_account_id();
-        require!(vapi.coder_info.account_id == account_id, "Only coder can transfer ownership");
-
-        vapi.coder_info.account_id = new_coder_id;
-        self.vapis.insert(&vapi_id, &vapi);
+        reviewer_info.acc_reward_per_share += reward_per_share(total_delegator_reward_amount, reviewer_info.total_delegator_deposit_amount);
+        self.reviewers.insert(reviewer_id, &reviewer_info);
     }
 
     pub fn delegator_request_refund(&mut self, reviewer_id: &AccountId, amount: U128) -> Promise {
@@ -264,65 +393,7 @@ impl TicleCore {
         self.compound(reviewer_id);
 
         let sender_id = env::predecessor_account_id();
-
-        let mut reviewer_info = self.reviewers.get(&reviewer_id).expect("Reviewer not found");
-        let mut delegator_info = reviewer_info.delegators.get(&sender_id).expect("Delegator not found");
-        let reward = self.pending_reward(delegator_info.deposit_info.deposit_amount, delegator_info.deposit_info.reward_debt, reviewer_info.acc_reward_per_share);
-
-        let delegator_balance = delegator_info.deposit_info.deposit_amount + reward;
-        require!(delegator_balance >= amount, "Delegator balance is less than the amount to refund");
-
-        if reviewer_info.pending_amount < amount {
-            let pending_amount = reviewer_info.pending_amount;
-            let total_deposit_amount = reviewer_info.total_delegator_deposit_amount - pending_amount;
-            
-            let origin_remain_amount = amount - pending_amount;
-            let mut remain_amount = origin_remain_amount;
-            for (vapi_id, mut deposit_info) in reviewer_info.deposit_vapis.to_vec() {
-                let deposit_rate = (deposit_info.deposit_amount as f64) / (total_deposit_amount as f64);
-                let decrease_amount = (origin_remain_amount as f64 * deposit_rate).floor() as Balance;
-
-                deposit_info.deposit_amount -= decrease_amount;
-                deposit_info.reward_debt = deposit_info.deposit_amount * reviewer_info.acc_reward_per_share / 1_000_000_000_000;
-                reviewer_info.deposit_vapis.insert(&vapi_id, &deposit_info);
-
-                let mut vapi = self.vapis.get(&vapi_id).unwrap();
-                vapi.total_deposit_amount -= decrease_amount;
-                self.vapis.insert(&vapi_id, &vapi);
-
-                remain_amount -= decrease_amount;
-            }
-
-            if remain_amount > 0 {
-                for (vapi_id, mut deposit_info) in reviewer_info.deposit_vapis.to_vec() {
-                    if deposit_info.deposit_amount >= remain_amount {
-                        deposit_info.deposit_amount -= remain_amount;
-                        deposit_info.reward_debt = deposit_info.deposit_amount * reviewer_info.acc_reward_per_share / 1_000_000_000_000;
-                        reviewer_info.deposit_vapis.insert(&vapi_id, &deposit_info);
-
-                        let mut vapi = self.vapis.get(&vapi_id).unwrap();
-                        vapi.total_deposit_amount -= remain_amount;
-                        self.vapis.insert(&vapi_id, &vapi);
-                        break;
-                    }
-                }
-            }
-
-            reviewer_info.total_delegator_deposit_amount -= amount - pending_amount;
-            reviewer_info.pending_amount = 0;
-        } else {
-            reviewer_info.pending_amount -= amount;
-        }
-
-        delegator_info.deposit_info.deposit_amount = delegator_balance - amount;
-        delegator_info.deposit_info.reward_debt = delegator_info.deposit_info.deposit_amount * reviewer_info.acc_reward_per_share / 1_000_000_000_000;
-        
-        delegator_info.refunding_amount += amount;
-        delegator_info.refunding_start_timestamp = env::block_timestamp_ms();
-        reviewer_info.delegators.insert(&sender_id, &delegator_info);
-        
-        reviewer_info.total_delegator_deposit_amount -= amount;
-        self.reviewers.insert(&reviewer_id, &reviewer_info);
+        self.internal_request_refund(reviewer_id, &sender_id, &sender_id, amount);
 
         // TODO: should emit success event
         return Promise::new(sender_id.clone());
@@ -331,7 +402,7 @@ impl TicleCore {
     pub fn delegator_claim_refund(&mut self, reviewer_id: &AccountId) -> Promise {
         let delegator_id = env::predecessor_account_id();
 
-        let mut reviewer_info = self.reviewers.get(&reviewer_id).expect("Reviewer not found");
+        let mut reviewer_info = self.reviewers.get(reviewer_id).expect("Reviewer not found");
         let mut delegator_info = reviewer_info.delegators.get(&delegator_id).expect("Delegator not found");
 
         let refunding_amount = delegator_info.refunding_amount;
@@ -339,11 +410,11 @@ impl TicleCore {
             return Promise::new(delegator_id);
         }
 
-        require!(env::block_timestamp_ms() - delegator_info.refunding_start_timestamp >= 60 * 1_000, "Refunding period is less than 60 seconds");
+        require!(env::block_timestamp_ms() - delegator_info.refunding_start_timestamp >= REFUNDING_PERIOD, "Refunding period is less than 60 seconds");
 
         delegator_info.refunding_amount = 0;
         reviewer_info.delegators.insert(&delegator_id, &delegator_info);
-        self.reviewers.insert(&reviewer_id, &reviewer_info);
+        self.reviewers.insert(reviewer_id, &reviewer_info);
 
         return ext_ft_core::ext(self.token_id.clone())
             .with_attached_deposit(NearToken::from_yoctonear(1))
@@ -354,7 +425,7 @@ impl TicleCore {
                     .with_static_gas(Gas::from_tgas(20))
                     .callback_delegator_claim_refund(
                         &delegator_id, 
-                        &reviewer_id, 
+                        reviewer_id, 
                         refunding_amount, 
                     )
             );
@@ -362,33 +433,62 @@ impl TicleCore {
 
     #[private]
     pub fn callback_delegator_claim_refund(&mut self, delegator_id: &AccountId, reviewer_id: &AccountId, refunding_amount: Balance) -> Promise {
-        let mut reviewer_info = self.reviewers.get(&reviewer_id).unwrap();
-        let mut delegator_info = reviewer_info.delegators.get(&delegator_id).unwrap();
-
         const REFUND_TRANSFER_PROMISE_INDEX: u64 = 0;
+        let Some((mut reviewer_info, mut delegator_info)) = self.reviewers
+            .get(reviewer_id)
+            .and_then(|reviewer_info| reviewer_info.delegators.get(delegator_id).map(|delegator_info| (reviewer_info, delegator_info)))
+        else {
+            // The record was removed with the reviewer meanwhile, a failed refund is kept with the exit refunds
+            if let PromiseResult::Failed = env::promise_result(REFUND_TRANSFER_PROMISE_INDEX) {
+                self.internal_add_exit_refund(delegator_id, refunding_amount);
+            }
+            return Promise::new(delegator_id.clone());
+        };
+
         match env::promise_result(REFUND_TRANSFER_PROMISE_INDEX) {
             PromiseResult::Failed => {
                 delegator_info.refunding_amount += refunding_amount;
-                reviewer_info.delegators.insert(&delegator_id, &delegator_info);
-                self.reviewers.insert(&reviewer_id, &reviewer_info);
+                reviewer_info.delegators.insert(delegator_id, &delegator_info);
+                self.reviewers.insert(reviewer_id, &reviewer_info);
                 return Promise::new(delegator_id.clone());
             }
             PromiseResult::Successful(_) => {
                 if delegator_info.refunding_amount == 0 {
                     delegator_info.refunding_start_timestamp = 0;
 
+                    let initial_storage_usage = env::storage_usage();
                     if delegator_info.deposit_info.deposit_amount == 0 {
-                        reviewer_info.delegators.remove(&delegator_id);
+                        reviewer_info.delegators.remove(delegator_id);
                     } else {
-                        reviewer_info.delegators.insert(&delegator_id, &delegator_info);
+                        reviewer_info.delegators.insert(delegator_id, &delegator_info);
                     }
-                    self.reviewers.insert(&reviewer_id, &reviewer_info);
+                    self.reviewers.insert(reviewer_id, &reviewer_info);
+                    self.internal_update_storage_usage(delegator_id, initial_storage_usage);
                 }
                 return Promise::new(delegator_id.clone());   
             }
         }
     }
 
+    #[private]
+    pub fn callback_burn(&mut self, amount: Balance) {
+        const BURN_PROMISE_INDEX: u64 = 0;
+        match env::promise_result(BURN_PROMISE_INDEX) {
+            PromiseResult::Failed => {
+                log!("[callback_burn] burn failed, pending amount: {}", amount);
+                self.pending_burn_amount += amount;
+            }
+            PromiseResult::Successful(_) => {
+                self.total_burned_amount += amount;
+            }
+        }
+    }
+
+    pub fn retry_pending_burn(&mut self) -> Promise {
+        require!(self.pending_burn_amount > 0, "No pending burn");
+        return self.internal_burn(0);
+    }
+
     #[payable]
     pub fn withdraw_from_vapi(&mut self, vapi_id: String, amount: U128) -> Promise {
         log!("[withdraw_from_vapi] vapi_id: {}", vapi_id);
@@ -405,15 +505,17 @@ impl TicleCore {
         require!(deposit_info.deposit_amount >= amount, "deposit amount must be greater than amount");
         deposit_info.deposit_amount -= amount;
 
+        let initial_storage_usage = env::storage_usage();
         if deposit_info.deposit_amount == 0 {
             reviewer_info.deposit_vapis.remove(&vapi_id);
         } else {
-            deposit_info.reward_debt = deposit_info.deposit_amount * reviewer_info.acc_reward_per_share / 1_000_000_000_000;
+            deposit_info.reward_debt = accumulated_reward(deposit_info.deposit_amount, reviewer_info.acc_reward_per_share);
             reviewer_info.deposit_vapis.insert(&vapi_id, &deposit_info);
         }
 
         reviewer_info.pending_amount += amount;
         self.reviewers.insert(&reviewer_id, &reviewer_info);
+        self.internal_update_storage_usage(&reviewer_id, initial_storage_usage);
 
         vapi.total_deposit_amount -= amount;
         self.vapis.insert(&vapi_id, &vapi);
@@ -424,11 +526,98 @@ impl TicleCore {
 
 #[near]
 impl TicleCore {
-    fn internal_deposit_to_reviewer(&mut self, sender_id: &AccountId, reviewer_id: &AccountId, amount: Balance) -> Promise {
+    /// Takes `amount` out of the delegation of `delegator_id`, withdrawing from the VAPI deposits when the pending
+    /// amount falls short, and starts its unbonding for `receiver_id`. The caller compounds the reviewer first.
+    fn internal_request_refund(&mut self, reviewer_id: &AccountId, delegator_id: &AccountId, receiver_id: &AccountId, amount: Balance) {
+        let mut reviewer_info = self.reviewers.get(reviewer_id).expect("Reviewer not found");
+        let mut delegator_info = reviewer_info.delegators.get(delegator_id).expect("Delegator not found");
+        let reward = self.pending_reward(delegator_info.deposit_info.deposit_amount, delegator_info.deposit_info.reward_debt, reviewer_info.acc_reward_per_share);
+
+        let delegator_balance = delegator_info.deposit_info.deposit_amount + reward;
+        require!(delegator_balance >= amount, "Delegator balance is less than the amount to refund");
+
+        if reviewer_info.pending_amount < amount {
+            let pending_amount = reviewer_info.pending_amount;
+            let total_deposit_amount = reviewer_info.total_delegator_deposit_amount - pending_amount;
+            
+            let origin_remain_amount = amount - pending_amount;
+            let mut remain_amount = origin_remain_amount;
+            for (vapi_id, mut deposit_info) in reviewer_info.deposit_vapis.to_vec() {
+                let deposit_rate = (deposit_info.deposit_amount as f64) / (total_deposit_amount as f64);
+                let decrease_amount = (origin_remain_amount as f64 * deposit_rate).floor() as Balance;
+
+                deposit_info.deposit_amount -= decrease_amount;
+                deposit_info.reward_debt = accumulated_reward(deposit_info.deposit_amount, reviewer_info.acc_reward_per_share);
+                reviewer_info.deposit_vapis.insert(&vapi_id, &deposit_info);
+
+                let mut vapi = self.vapis.get(&vapi_id).unwrap();
+                vapi.total_deposit_amount -= decrease_amount;
+                self.vapis.insert(&vapi_id, &vapi);
+
+                remain_amount -= decrease_amount;
+            }
+
+            if remain_amount > 0 {
+                for (vapi_id, mut deposit_info) in reviewer_info.deposit_vapis.to_vec() {
+                    if deposit_info.deposit_amount >= remain_amount {
+                        deposit_info.deposit_amount -= remain_amount;
+                        deposit_info.reward_debt = accumulated_reward(deposit_info.deposit_amount, reviewer_info.acc_reward_per_share);
+                        reviewer_info.deposit_vapis.insert(&vapi_id, &deposit_info);
+
+                        let mut vapi = self.vapis.get(&vapi_id).unwrap();
+                        vapi.total_deposit_amount -= remain_amount;
+                        self.vapis.insert(&vapi_id, &vapi);
+                        break;
+                    }
+                }
+            }
+
+            reviewer_info.total_delegator_deposit_amount -= amount - pending_amount;
+            reviewer_info.pending_amount = 0;
+        } else {
+            reviewer_info.pending_amount -= amount;
+        }
+
+        delegator_info.deposit_info.deposit_amount = delegator_balance - amount;
+        delegator_info.deposit_info.reward_debt = accumulated_reward(delegator_info.deposit_info.deposit_amount, reviewer_info.acc_reward_per_share);
+        
+        if receiver_id == delegator_id {
+            delegator_info.refunding_amount += amount;
+            delegator_info.refunding_start_timestamp = env::block_timestamp_ms();
+            reviewer_info.delegators.insert(delegator_id, &delegator_info);
+        } else {
+            reviewer_info.delegators.insert(delegator_id, &delegator_info);
+
+            let mut receiver_info = reviewer_info.delegators.get(receiver_id).unwrap_or(DelegatorInfo {
+                deposit_info: DepositInfo {
+                    deposit_amount: 0,
+                    reward_debt: 0,
+                },
+                refunding_amount: 0,
+                refunding_start_timestamp: 0,
+            });
+            receiver_info.refunding_amount += amount;
+            receiver_info.refunding_start_timestamp = env::block_timestamp_ms();
+            reviewer_info.delegators.insert(receiver_id, &receiver_info);
+        }
+        
+        reviewer_info.total_delegator_deposit_amount -= amount;
+        self.reviewers.insert(reviewer_id, &reviewer_info);
+        delegation_mt::emit_mt_burn(delegator_id, reviewer_id, amount);
+    }
+
+    fn internal_deposit_to_reviewer(&mut self, sender_id: &AccountId, reviewer_id: &AccountId, amount: Balance) -> Result<Promise, String> {
         log!("[internal_deposit] deposit to reviewer: {}, {}", sender_id, reviewer_id);
+        if !self.accounts.contains_key(sender_id) {
+            return Err(format!("The account {} is not registered", sender_id));
+        }
 
-        let mut reviewer_info = self.reviewers.get(&reviewer_id).expect("Reviewer not found");
-        let mut delegator_info = reviewer_info.delegators.get(&sender_id).unwrap_or(DelegatorInfo {
+        let mut reviewer_info = self.reviewers.get(reviewer_id).ok_or("Reviewer not found")?;
+        if reviewer_info.exit_timestamp.is_some() {
+            return Err("Reviewer is deregistering".to_string());
+        }
+        let is_new_delegator = reviewer_info.delegators.get(sender_id).is_none();
+        let mut delegator_info = reviewer_info.delegators.get(sender_id).unwrap_or(DelegatorInfo {
             deposit_info: DepositInfo {
                 deposit_amount: 0,
                 reward_debt: 0,
@@ -443,48 +632,125 @@ impl TicleCore {
             reviewer_info.acc_reward_per_share
         );
         delegator_info.deposit_info.deposit_amount += amount + reward;
-        delegator_info.deposit_info.reward_debt = delegator_info.deposit_info.deposit_amount * reviewer_info.acc_reward_per_share / 1_000_000_000_000;
-        reviewer_info.delegators.insert(&sender_id, &delegator_info);
+        delegator_info.deposit_info.reward_debt = accumulated_reward(delegator_info.deposit_info.deposit_amount, reviewer_info.acc_reward_per_share);
+
+        let initial_storage_usage = env::storage_usage();
+        reviewer_info.delegators.insert(sender_id, &delegator_info);
+        if let Err(err) = self.internal_try_update_storage_usage(sender_id, initial_storage_usage) {
+            // Only a new delegator record takes additional storage, so dropping it restores the previous state
+            if is_new_delegator {
+                reviewer_info.delegators.remove(sender_id);
+            }
+            return Err(err);
+        }
         
         reviewer_info.pending_amount += amount;
         reviewer_info.total_delegator_deposit_amount += amount + reward;
-        self.reviewers.insert(&reviewer_id, &reviewer_info);
+        self.reviewers.insert(reviewer_id, &reviewer_info);
+        delegation_mt::emit_mt_mint(sender_id, reviewer_id, amount);
 
         // TODO: should emit success event
-        return Promise::new(reviewer_id.clone());
+        return Ok(Promise::new(reviewer_id.clone()));
     }
 
-    fn internal_settlement(&mut self, sender_id: &AccountId, vapi_ids: Vec<String>, amounts: Vec<U128>) -> Promise {
-        log!("[internal_settlement]");
-        require!(*sender_id == self.owner_id, "Only owner can settle");
-        require!(vapi_ids.len() == amounts.len(), "vapi_ids and amounts must have the same length");
+    fn internal_settlement(&mut self, sender_id: &AccountId, settlement_id: u64, vapi_ids: Vec<String>, amounts: Vec<U128>, transferred_amount: Balance) -> Result<Promise, String> {
+        log!("[internal_settlement] settlement_id: {}", settlement_id);
+        if *sender_id != self.owner_id {
+            return Err("Only owner can settle".to_string());
+        }
+        if vapi_ids.len() != amounts.len() {
+            return Err("vapi_ids and amounts must have the same length".to_string());
+        }
+        if let Some(vapi_id) = vapi_ids.iter().find(|vapi_id| !self.vapis.contains_key(vapi_id)) {
+            return Err(format!("VAPI not found: {}", vapi_id));
+        }
+        if self.settlements.contains_key(&settlement_id) {
+            return Err(format!("Settlement {} is already processed", settlement_id));
+        }
+
+        let settlement_amount = amounts.iter().try_fold(0 as Balance, |total, amount| total.checked_add(amount.0)).ok_or("Settlement amounts overflow")?;
+        if settlement_amount > transferred_amount {
+            return Err(format!("Settlement amounts {} exceed the transferred amount {}", settlement_amount, transferred_amount));
+        }
+        // Anything transferred on top of the settled amounts is kept by the treasury
+        let surplus_amount = transferred_amount - settlement_amount;
+
+        // The receipt only has fixed size fields left to fill in, so its storage can be charged before distributing
+        let mut receipt = SettlementReceipt {
+            vapi_ids: vapi_ids.clone(),
+            amounts: amounts.iter().map(|amount| amount.0).collect(),
+            reviewer_fee_amount: 0,
+            burn_amount: 0,
+            treasury_amount: 0,
+            surplus_amount,
+            timestamp: env::block_timestamp_ms(),
+        };
+        let initial_storage_usage = env::storage_usage();
+        self.settlements.insert(&settlement_id, &receipt);
+        if let Err(err) = self.internal_try_update_storage_usage(sender_id, initial_storage_usage) {
+            self.settlements.remove(&settlement_id);
+            return Err(err);
+        }
+
+        let distribution = self.internal_distribute(&vapi_ids, &receipt.amounts);
+        self.treasury += surplus_amount;
+
+        receipt.reviewer_fee_amount = distribution.reviewer_fee_amount;
+        receipt.burn_amount = distribution.burn_amount;
+        receipt.treasury_amount = distribution.treasury_amount + surplus_amount;
+        self.settlements.insert(&settlement_id, &receipt);
 
-        let mut total_burn_amount: u128 = 0;
-        let mut total_treasury: Balance = 0;
+        return Ok(self.internal_burn(distribution.burn_amount));
+    }
+
+    /// Splits each VAPI amount into the coder reward, the reviewer fee and the burn.
+    /// The reviewer fee of a VAPI without deposits goes to the treasury.
+    fn internal_distribute(&mut self, vapi_ids: &[String], amounts: &[Balance]) -> Distribution {
+        let mut distribution = Distribution {
+            reviewer_fee_amount: 0,
+            burn_amount: 0,
+            treasury_amount: 0,
+        };
         for (vapi_id, amount) in vapi_ids.iter().zip(amounts.iter()) {
-            let amount: Balance = amount.0;
+            let amount: Balance = *amount;
             let reviewer_fee_amount = amount * 39 / 100;
-            let burn_amount = amount * 1 / 100;
+            let burn_amount = amount / 100;
 
-            let mut vapi = self.vapis.get(&vapi_id).expect("VAPI not found");
+            let mut vapi = self.vapis.get(vapi_id).expect("VAPI not found");
             
-            vapi.coder_info.unclaimed_reward_amount += amount - reviewer_fee_amount - burn_amount;
+            vapi.distribute_coder_revenue(amount - reviewer_fee_amount - burn_amount);
 
             if vapi.total_deposit_amount == 0 {
-                total_treasury += reviewer_fee_amount;
+                distribution.treasury_amount += reviewer_fee_amount;
             } else {
-                vapi.acc_reward_per_share += reviewer_fee_amount * 1_000_000_000_000 / vapi.total_deposit_amount;
+                vapi.acc_reward_per_share += reward_per_share(reviewer_fee_amount, vapi.total_deposit_amount);
             }
             
-            self.vapis.insert(&vapi_id, &vapi);
-            total_burn_amount += burn_amount;
+            self.vapis.insert(vapi_id, &vapi);
+            distribution.reviewer_fee_amount += reviewer_fee_amount;
+            distribution.burn_amount += burn_amount;
         }
 
-        self.treasury += total_treasury;
+        self.treasury += distribution.treasury_amount;
+        return distribution;
+    }
+
+    /// Burns `amount` together with any burn that previously failed
+    fn internal_burn(&mut self, amount: Balance) -> Promise {
+        let burn_amount = amount + self.pending_burn_amount;
+        self.pending_burn_amount = 0;
+        if burn_amount == 0 {
+            return Promise::new(env::current_account_id());
+        }
 
         return ext_ft_burn::ext(self.token_id.clone())
             .with_attached_deposit(NearToken::from_yoctonear(1))
             .with_static_gas(Gas::from_tgas(20))
-            .burn(U128(total_burn_amount));
+            .burn(U128(burn_amount))
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(Gas::from_tgas(10))
+                    .callback_burn(burn_amount)
+            );
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,236 @@
+use crate::*;
+
+/// Virtual shares and amount of every pool, so a donation to an almost empty pool mostly goes to them
+/// instead of rounding the shares of the next staker down
+const LIQUID_VIRTUAL_OFFSET: Balance = 1_000_000_000_000;
+
+#[ext_contract(ext_liquid_token)]
+pub trait LiquidToken {
+    fn mint(&mut self, account_id: AccountId, amount: U128);
+}
+
+/// Liquid shares of a reviewer's delegation, issued by `token_id` which holds the pooled delegation in core
+#[near(serializers = [borsh])]
+pub struct LiquidPool {
+    pub(crate) token_id: AccountId,
+    pub(crate) total_shares: Balance,
+}
+
+#[near(serializers = [json])]
+pub struct LiquidPoolView {
+    pub token_id: AccountId,
+    pub total_shares: U128,
+    pub total_amount: U128,
+}
+
+impl TicleCore {
+    /// Value of the pooled delegation, the deposit of the token account together with its pending reward
+    fn liquid_pool_amount(&self, reviewer_id: &AccountId, pool: &LiquidPool) -> Balance {
+        let Some(reviewer_info) = self.reviewers.get(reviewer_id) else {
+            return 0;
+        };
+        return reviewer_info.delegators.get(&pool.token_id).map_or(0, |delegator_info| {
+            delegator_info.deposit_info.deposit_amount + self.pending_reward(delegator_info.deposit_info.deposit_amount, delegator_info.deposit_info.reward_debt, reviewer_info.acc_reward_per_share)
+        });
+    }
+
+    fn liquid_shares_for_amount(&self, pool: &LiquidPool, pool_amount: Balance, amount: Balance) -> Option<Balance> {
+        return mul_div(amount, pool.total_shares + LIQUID_VIRTUAL_OFFSET, pool_amount + LIQUID_VIRTUAL_OFFSET);
+    }
+
+    fn liquid_amount_for_shares(&self, pool: &LiquidPool, pool_amount: Balance, shares: Balance) -> Option<Balance> {
+        return mul_div(shares, pool_amount + LIQUID_VIRTUAL_OFFSET, pool.total_shares + LIQUID_VIRTUAL_OFFSET);
+    }
+
+    /// Rewards of the VAPI deposits are compounded into the exchange rate before shares are priced
+    fn internal_compound_liquid_pool(&mut self, reviewer_id: &AccountId) {
+        if self.reviewers.get(reviewer_id).is_some_and(|reviewer_info| reviewer_info.total_delegator_deposit_amount > 0) {
+            self.compound(reviewer_id);
+        }
+    }
+
+    /// Delegates `amount` through the liquid pool of the reviewer and mints the shares to `sender_id`
+    pub(crate) fn internal_stake_liquid(&mut self, sender_id: &AccountId, reviewer_id: &AccountId, amount: Balance) -> Result<Promise, String> {
+        if !self.accounts.contains_key(sender_id) {
+            return Err(format!("The account {} is not registered", sender_id));
+        }
+        let mut pool = self.liquid_pools.get(reviewer_id).ok_or("Liquid pool not found")?;
+
+        self.internal_compound_liquid_pool(reviewer_id);
+        let pool_amount = self.liquid_pool_amount(reviewer_id, &pool);
+        let shares = self.liquid_shares_for_amount(&pool, pool_amount, amount).ok_or("Share amount overflow")?;
+        if shares == 0 {
+            return Err("The amount is too small to mint shares".to_string());
+        }
+
+        self.internal_deposit_to_reviewer(&pool.token_id, reviewer_id, amount)?;
+        pool.total_shares += shares;
+        self.liquid_pools.insert(reviewer_id, &pool);
+
+        log!("[internal_stake_liquid] reviewer_id: {}, amount: {}, shares: {}", reviewer_id, amount, shares);
+        return Ok(ext_liquid_token::ext(pool.token_id.clone())
+            .with_static_gas(Gas::from_tgas(10))
+            .mint(sender_id.clone(), U128(shares))
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(Gas::from_tgas(20))
+                    .callback_liquid_mint(reviewer_id, sender_id, U128(shares))
+            ));
+    }
+
+    /// Burns `shares` of the pool and moves their value into the unbonding refund of `account_id`,
+    /// which is claimed with `delegator_claim_refund`. The caller accounts for the storage.
+    fn internal_redeem_liquid_shares(&mut self, reviewer_id: &AccountId, account_id: &AccountId, shares: Balance) -> Balance {
+        let mut pool = self.liquid_pools.get(reviewer_id).expect("Liquid pool not found");
+        require!(shares > 0 && shares <= pool.total_shares, "Invalid shares");
+
+        self.internal_compound_liquid_pool(reviewer_id);
+        let amount = self.liquid_amount_for_shares(&pool, self.liquid_pool_amount(reviewer_id, &pool), shares).expect("Share amount overflow");
+        pool.total_shares -= shares;
+        self.liquid_pools.insert(reviewer_id, &pool);
+
+        if amount > 0 {
+            self.internal_request_refund(reviewer_id, &pool.token_id, account_id, amount);
+        }
+        log!("[internal_redeem_liquid_shares] reviewer_id: {}, shares: {}, amount: {}", reviewer_id, shares, amount);
+        return amount;
+    }
+}
+
+#[near]
+impl TicleCore {
+    /// Registers the liquid token contract of a reviewer, deployed with this contract and the reviewer.
+    /// The token account must be registered, the pool and its delegation are charged to its storage deposit.
+    pub fn create_liquid_pool(&mut self, reviewer_id: AccountId, token_id: AccountId) {
+        self.assert_owner();
+        self.assert_registered(&token_id);
+        self.assert_reviewer_active(&self.reviewers.get(&reviewer_id).expect("Reviewer not found"));
+        require!(!self.liquid_pools.contains_key(&reviewer_id), "Liquid pool already exists");
+
+        let initial_storage_usage = env::storage_usage();
+        self.liquid_pools.insert(&reviewer_id, &LiquidPool { token_id: token_id.clone(), total_shares: 0 });
+        self.internal_update_storage_usage(&token_id, initial_storage_usage);
+        log!("[create_liquid_pool] reviewer_id: {}, token_id: {}", reviewer_id, token_id);
+    }
+
+    /// Called by the liquid token after burning `shares` of `account_id`, returns the amount put into unbonding
+    pub fn redeem_liquid_shares(&mut self, reviewer_id: AccountId, account_id: AccountId, shares: U128) -> U128 {
+        let pool = self.liquid_pools.get(&reviewer_id).expect("Liquid pool not found");
+        require!(env::predecessor_account_id() == pool.token_id, "Only the liquid token can redeem shares");
+        self.assert_registered(&account_id);
+
+        let initial_storage_usage = env::storage_usage();
+        let amount = self.internal_redeem_liquid_shares(&reviewer_id, &account_id, shares.into());
+        self.internal_update_storage_usage(&account_id, initial_storage_usage);
+        return U128(amount);
+    }
+
+    /// Shares that failed to mint are redeemed right away, so the stake goes back to the staker through unbonding
+    #[private]
+    pub fn callback_liquid_mint(&mut self, reviewer_id: &AccountId, account_id: &AccountId, shares: U128) {
+        const MINT_PROMISE_INDEX: u64 = 0;
+        if let PromiseResult::Successful(_) = env::promise_result(MINT_PROMISE_INDEX) {
+            return;
+        }
+
+        log!("[callback_liquid_mint] mint failed, reviewer_id: {}, account_id: {}", reviewer_id, account_id);
+        let initial_storage_usage = env::storage_usage();
+        self.internal_redeem_liquid_shares(reviewer_id, account_id, shares.into());
+        if let Err(err) = self.internal_try_update_storage_usage(account_id, initial_storage_usage) {
+            log!("[callback_liquid_mint] {}", err);
+        }
+    }
+
+    pub fn get_liquid_pool(&self, reviewer_id: AccountId) -> Option<LiquidPoolView> {
+        let pool = self.liquid_pools.get(&reviewer_id)?;
+        return Some(LiquidPoolView {
+            total_amount: U128(self.liquid_pool_amount(&reviewer_id, &pool)),
+            total_shares: U128(pool.total_shares),
+            token_id: pool.token_id,
+        });
+    }
+
+    /// Current value of `shares`, not counting the VAPI rewards compounded on the next stake or redemption
+    pub fn get_liquid_share_amount(&self, reviewer_id: AccountId, shares: U128) -> U128 {
+        let Some(pool) = self.liquid_pools.get(&reviewer_id) else {
+            return U128(0);
+        };
+        if pool.total_shares == 0 {
+            return U128(0);
+        }
+        return U128(self.liquid_amount_for_shares(&pool, self.liquid_pool_amount(&reviewer_id, &pool), shares.0).expect("Share amount overflow"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_contract_standards::storage_management::StorageManagement;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    const ONE_TIC: Balance = 1_000_000_000_000_000_000_000_000;
+
+    fn set_predecessor(predecessor_account_id: AccountId, attached_deposit: NearToken) {
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(predecessor_account_id)
+            .attached_deposit(attached_deposit)
+            .build());
+    }
+
+    fn register(contract: &mut TicleCore, account_id: AccountId) {
+        set_predecessor(account_id, NearToken::from_near(1));
+        contract.storage_deposit(None, None);
+    }
+
+    #[test]
+    fn test_stake_multiple_stakers() {
+        let (owner, reviewer, token, alice, bob) = (accounts(0), accounts(1), accounts(2), accounts(3), accounts(4));
+        set_predecessor(owner.clone(), NearToken::from_yoctonear(0));
+        let mut contract = TicleCore::new("tic".parse().unwrap(), owner.clone());
+        for account_id in [reviewer.clone(), token.clone(), alice.clone(), bob.clone()] {
+            register(&mut contract, account_id);
+        }
+        set_predecessor(reviewer.clone(), NearToken::from_yoctonear(0));
+        contract.create_reviewer(&reviewer);
+        set_predecessor(owner, NearToken::from_yoctonear(0));
+        contract.create_liquid_pool(reviewer.clone(), token.clone());
+
+        let stakes = [(alice, 10_000_000 * ONE_TIC), (bob, 25_000_000 * ONE_TIC)];
+        for (staker, amount) in stakes.iter() {
+            contract.internal_stake_liquid(staker, &reviewer, *amount).unwrap();
+        }
+
+        let pool = contract.get_liquid_pool(reviewer.clone()).unwrap();
+        assert_eq!(pool.total_shares.0, 35_000_000 * ONE_TIC);
+        assert_eq!(pool.total_amount.0, 35_000_000 * ONE_TIC);
+        for (_, amount) in stakes.iter() {
+            assert_eq!(contract.get_liquid_share_amount(reviewer.clone(), U128(*amount)).0, *amount);
+        }
+    }
+
+    #[test]
+    fn test_stake_after_donation() {
+        let (owner, reviewer, token, alice, bob) = (accounts(0), accounts(1), accounts(2), accounts(3), accounts(4));
+        set_predecessor(owner.clone(), NearToken::from_yoctonear(0));
+        let mut contract = TicleCore::new("tic".parse().unwrap(), owner.clone());
+        for account_id in [reviewer.clone(), token.clone(), alice.clone(), bob.clone()] {
+            register(&mut contract, account_id);
+        }
+        set_predecessor(reviewer.clone(), NearToken::from_yoctonear(0));
+        contract.create_reviewer(&reviewer);
+        set_predecessor(owner, NearToken::from_yoctonear(0));
+        contract.create_liquid_pool(reviewer.clone(), token.clone());
+
+        // Alice takes the first share and inflates the pool, as a delegation ending up with the token account would
+        contract.internal_stake_liquid(&alice, &reviewer, 1).unwrap();
+        contract.internal_deposit_to_reviewer(&token, &reviewer, ONE_TIC).unwrap();
+        contract.internal_stake_liquid(&bob, &reviewer, ONE_TIC).unwrap();
+
+        // The donation goes to the virtual shares, so bob keeps his stake and alice can't take it back
+        let pool = contract.get_liquid_pool(reviewer.clone()).unwrap();
+        let bob_shares = pool.total_shares.0 - 1;
+        assert!(contract.get_liquid_share_amount(reviewer.clone(), U128(bob_shares)).0 > ONE_TIC - ONE_TIC / 1_000_000);
+        assert!(contract.get_liquid_share_amount(reviewer, U128(1)).0 < ONE_TIC / 1_000_000);
+    }
+}
@@ -0,0 +1,70 @@
+use crate::*;
+
+#[near(serializers = [borsh, json])]
+pub struct OwnershipTransfer {
+    pub new_coder_id: AccountId,
+    /// Pays out the unclaimed rewards of the current coder on acceptance instead of leaving them claimable
+    pub settle_rewards: bool,
+}
+
+#[near]
+impl TicleCore {
+    /// Proposes `new_coder_id` as the coder of a VAPI, replacing any pending proposal.
+    /// The proposed account must be registered and takes over only once it accepts.
+    pub fn propose_ownership_transfer(&mut self, vapi_id: String, new_coder_id: AccountId, settle_rewards: bool) {
+        let coder_id = env::predecessor_account_id();
+        let mut vapi = self.vapis.get(&vapi_id).expect("Vertical API not found");
+        require!(vapi.coder_info.account_id == coder_id, "Only coder can transfer ownership");
+        require!(new_coder_id != coder_id, "The new coder must be another account");
+        self.assert_registered(&new_coder_id);
+
+        let initial_storage_usage = env::storage_usage();
+        vapi.pending_transfer = Some(OwnershipTransfer { new_coder_id: new_coder_id.clone(), settle_rewards });
+        self.vapis.insert(&vapi_id, &vapi);
+        self.internal_update_storage_usage(&coder_id, initial_storage_usage);
+        log!("[propose_ownership_transfer] vapi_id: {}, new_coder_id: {}", vapi_id, new_coder_id);
+    }
+
+    /// Withdraws a pending proposal, callable by the coder or declined by the proposed account
+    pub fn cancel_ownership_transfer(&mut self, vapi_id: String) {
+        let account_id = env::predecessor_account_id();
+        let mut vapi = self.vapis.get(&vapi_id).expect("Vertical API not found");
+        let transfer = vapi.pending_transfer.take().expect("No pending ownership transfer");
+        require!(vapi.coder_info.account_id == account_id || transfer.new_coder_id == account_id, "Only coder or the proposed coder can cancel the transfer");
+
+        let initial_storage_usage = env::storage_usage();
+        self.vapis.insert(&vapi_id, &vapi);
+        self.internal_update_storage_usage(&vapi.coder_info.account_id, initial_storage_usage);
+        log!("[cancel_ownership_transfer] vapi_id: {}", vapi_id);
+    }
+
+    /// Hands the VAPI and its token over to the proposed coder. Rewards accrued by the previous coder are
+    /// transferred to them when the proposal settles them, otherwise they stay claimable with `claim_vapi_reward`.
+    pub fn accept_ownership_transfer(&mut self, vapi_id: String) -> PromiseOrValue<()> {
+        let new_coder_id = env::predecessor_account_id();
+        let mut vapi = self.vapis.get(&vapi_id).expect("Vertical API not found");
+        let transfer = vapi.pending_transfer.take().expect("No pending ownership transfer");
+        require!(transfer.new_coder_id == new_coder_id, "Only the proposed coder can accept the transfer");
+
+        let previous_coder_id = vapi.coder_info.account_id.clone();
+        let reward_amount = if transfer.settle_rewards { vapi.take_reward(&previous_coder_id) } else { 0 };
+        let initial_storage_usage = env::storage_usage();
+        self.vapis.insert(&vapi_id, &vapi);
+        self.internal_update_storage_usage(&previous_coder_id, initial_storage_usage);
+
+        let (_, approved_account_ids) = self.internal_transfer_vapi(&previous_coder_id, &new_coder_id, &vapi_id, None, None);
+        if let Some(approved_account_ids) = approved_account_ids {
+            vapi_nft::refund_approved_account_ids(previous_coder_id.clone(), &approved_account_ids);
+        }
+        log!("[accept_ownership_transfer] vapi_id: {}, previous_coder_id: {}, settled_amount: {}", vapi_id, previous_coder_id, reward_amount);
+
+        if reward_amount == 0 {
+            return PromiseOrValue::Value(());
+        }
+        return self.internal_transfer_vapi_reward(vapi_id, &previous_coder_id, reward_amount).into();
+    }
+
+    pub fn get_pending_ownership_transfer(&self, vapi_id: String) -> Option<OwnershipTransfer> {
+        return self.vapis.get(&vapi_id).and_then(|vapi| vapi.pending_transfer);
+    }
+}
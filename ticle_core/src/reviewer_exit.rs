@@ -0,0 +1,242 @@
+use crate::*;
+
+impl TicleCore {
+    pub(crate) fn assert_reviewer_active(&self, reviewer_info: &ReviewerInfo) {
+        require!(reviewer_info.exit_timestamp.is_none(), "Reviewer is deregistering");
+    }
+
+    fn internal_transfer_royalty(&mut self, reviewer_id: &AccountId, reviewer_info: &mut ReviewerInfo) -> Promise {
+        let royalty_amount = std::mem::take(&mut reviewer_info.royalty_amount);
+        if royalty_amount == 0 {
+            return Promise::new(reviewer_id.clone());
+        }
+
+        log!("[deregister_reviewer] reviewer_id: {}, royalty_amount: {}", reviewer_id, royalty_amount);
+        return ext_ft_core::ext(self.token_id.clone())
+            .with_attached_deposit(NearToken::from_yoctonear(1))
+            .with_static_gas(Gas::from_tgas(20))
+            .ft_transfer(reviewer_id.clone(), U128(royalty_amount), None)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(Gas::from_tgas(10))
+                    .callback_reviewer_royalty(reviewer_id, royalty_amount)
+            );
+    }
+
+    pub(crate) fn internal_add_exit_refund(&mut self, account_id: &AccountId, amount: Balance) {
+        let initial_storage_usage = env::storage_usage();
+        let exit_refund = self.exit_refunds.get(account_id).unwrap_or(0);
+        self.exit_refunds.insert(account_id, &(exit_refund + amount));
+        if let Err(err) = self.internal_try_update_storage_usage(account_id, initial_storage_usage) {
+            log!("[internal_add_exit_refund] {}", err);
+        }
+    }
+}
+
+#[near]
+impl TicleCore {
+    /// Starts the exit of a reviewer, callable by the reviewer or the owner. Every VAPI deposit goes back to
+    /// the pending amount, new delegations are refused and the royalties are paid out. Calling it again retries
+    /// a royalty payout that failed. Delegators are unbonded with `unbond_reviewer_delegators` afterwards.
+    pub fn deregister_reviewer(&mut self, reviewer_id: AccountId) -> Promise {
+        let account_id = env::predecessor_account_id();
+        require!(account_id == reviewer_id || account_id == self.owner_id, "Only reviewer or owner can deregister the reviewer");
+        if self.reviewers.get(&reviewer_id).expect("Reviewer not found").total_delegator_deposit_amount > 0 {
+            self.compound(&reviewer_id);
+        }
+
+        let mut reviewer_info = self.reviewers.get(&reviewer_id).unwrap();
+        let initial_storage_usage = env::storage_usage();
+        for (vapi_id, deposit_info) in reviewer_info.deposit_vapis.to_vec() {
+            let mut vapi = self.vapis.get(&vapi_id).expect("Vertical API not found");
+            vapi.total_deposit_amount -= deposit_info.deposit_amount;
+            self.vapis.insert(&vapi_id, &vapi);
+
+            reviewer_info.pending_amount += deposit_info.deposit_amount;
+            reviewer_info.deposit_vapis.remove(&vapi_id);
+        }
+        if reviewer_info.exit_timestamp.is_none() {
+            reviewer_info.exit_timestamp = Some(env::block_timestamp_ms());
+            reviewer_info.unbond_cursor = reviewer_info.delegators.len();
+        }
+        let promise = self.internal_transfer_royalty(&reviewer_id, &mut reviewer_info);
+        self.reviewers.insert(&reviewer_id, &reviewer_info);
+        self.internal_update_storage_usage(&reviewer_id, initial_storage_usage);
+        return promise;
+    }
+
+    #[private]
+    pub fn callback_reviewer_royalty(&mut self, reviewer_id: &AccountId, royalty_amount: Balance) {
+        const ROYALTY_TRANSFER_PROMISE_INDEX: u64 = 0;
+        if let PromiseResult::Successful(_) = env::promise_result(ROYALTY_TRANSFER_PROMISE_INDEX) {
+            return;
+        }
+
+        log!("[callback_reviewer_royalty] transfer failed, reviewer_id: {}", reviewer_id);
+        let mut reviewer_info = self.reviewers.get(reviewer_id).unwrap();
+        reviewer_info.royalty_amount += royalty_amount;
+        self.reviewers.insert(reviewer_id, &reviewer_info);
+    }
+
+    /// Moves the whole balance of the next `limit` delegators of a deregistering reviewer into unbonding,
+    /// claimable with `delegator_claim_refund`. Callable by anyone, returns the number of delegators still to visit.
+    /// The liquid pool isn't unbonded, its holders redeem their shares until the reviewer is removed.
+    pub fn unbond_reviewer_delegators(&mut self, reviewer_id: AccountId, limit: u32) -> u64 {
+        let mut reviewer_info = self.reviewers.get(&reviewer_id).expect("Reviewer not found");
+        require!(reviewer_info.exit_timestamp.is_some(), "Reviewer is not deregistering");
+        let liquid_token_id = self.liquid_pools.get(&reviewer_id).map(|pool| pool.token_id);
+
+        // Visited from the end, so a record removed by a claimed refund only swaps in one already visited
+        let mut index = reviewer_info.unbond_cursor.min(reviewer_info.delegators.len());
+        let end = index.saturating_sub(limit as u64);
+        let mut count = 0;
+        while index > end {
+            index -= 1;
+            let delegator_id = reviewer_info.delegators.keys_as_vector().get(index).unwrap();
+            let mut delegator_info = reviewer_info.delegators.values_as_vector().get(index).unwrap();
+            if delegator_info.deposit_info.deposit_amount == 0 || Some(&delegator_id) == liquid_token_id.as_ref() {
+                continue;
+            }
+
+            let reward = self.pending_reward(delegator_info.deposit_info.deposit_amount, delegator_info.deposit_info.reward_debt, reviewer_info.acc_reward_per_share);
+            // Rounding of the reward per share can leave the last delegators with slightly more than is pending
+            let amount = (delegator_info.deposit_info.deposit_amount + reward).min(reviewer_info.pending_amount);
+
+            delegator_info.deposit_info.deposit_amount = 0;
+            delegator_info.deposit_info.reward_debt = 0;
+            if delegator_info.refunding_amount == 0 {
+                delegator_info.refunding_start_timestamp = env::block_timestamp_ms();
+            }
+            delegator_info.refunding_amount += amount;
+            reviewer_info.delegators.insert(&delegator_id, &delegator_info);
+
+            reviewer_info.pending_amount -= amount;
+            reviewer_info.total_delegator_deposit_amount = reviewer_info.total_delegator_deposit_amount.saturating_sub(amount);
+            delegation_mt::emit_mt_burn(&delegator_id, &reviewer_id, amount);
+            count += 1;
+        }
+        reviewer_info.unbond_cursor = end;
+        self.reviewers.insert(&reviewer_id, &reviewer_info);
+
+        log!("[unbond_reviewer_delegators] reviewer_id: {}, count: {}, remaining: {}", reviewer_id, count, end);
+        return end;
+    }
+
+    /// Removes a deregistering reviewer in pages of `limit` delegators, once the royalties are paid and every
+    /// delegator is unbonded with its refund matured. Refunds not claimed yet and what is left of the liquid pool
+    /// delegation move to `claim_exit_refund`, so inactive delegators and share holders don't block the removal.
+    /// Callable by anyone, returns the number of delegators left and removes the reviewer with the last page.
+    /// Storage is released to the accounts that paid for it and the dust left pending goes to the treasury.
+    pub fn remove_reviewer(&mut self, reviewer_id: AccountId, limit: u32) -> u64 {
+        let mut reviewer_info = self.reviewers.get(&reviewer_id).expect("Reviewer not found");
+        require!(reviewer_info.exit_timestamp.is_some(), "Reviewer is not deregistering");
+        require!(reviewer_info.royalty_amount == 0, "Royalties are not paid out");
+        require!(reviewer_info.deposit_vapis.is_empty(), "VAPI deposits are not withdrawn");
+        require!(reviewer_info.unbond_cursor == 0, "Delegators are not unbonded");
+
+        // The pool goes first, so no share is redeemed against a delegation already moved out
+        if let Some(pool) = self.liquid_pools.get(&reviewer_id) {
+            let token_id = pool.token_id;
+            let mut refund_amount = 0;
+            let initial_storage_usage = env::storage_usage();
+            if let Some(delegator_info) = reviewer_info.delegators.remove(&token_id) {
+                let reward = self.pending_reward(delegator_info.deposit_info.deposit_amount, delegator_info.deposit_info.reward_debt, reviewer_info.acc_reward_per_share);
+                let amount = (delegator_info.deposit_info.deposit_amount + reward).min(reviewer_info.pending_amount);
+                reviewer_info.pending_amount -= amount;
+                reviewer_info.total_delegator_deposit_amount = reviewer_info.total_delegator_deposit_amount.saturating_sub(amount);
+                delegation_mt::emit_mt_burn(&token_id, &reviewer_id, amount);
+                refund_amount = amount + delegator_info.refunding_amount;
+            }
+            self.liquid_pools.remove(&reviewer_id);
+            if let Err(err) = self.internal_try_update_storage_usage(&token_id, initial_storage_usage) {
+                log!("[remove_reviewer] {}", err);
+            }
+            if refund_amount > 0 {
+                self.internal_add_exit_refund(&token_id, refund_amount);
+            }
+            log!("[remove_reviewer] reviewer_id: {}, liquid token_id: {}, amount: {}", reviewer_id, token_id, refund_amount);
+        }
+
+        // Taken from the end, so removing a record doesn't move the others
+        let mut count = 0;
+        while count < limit && !reviewer_info.delegators.is_empty() {
+            let index = reviewer_info.delegators.len() - 1;
+            let delegator_id = reviewer_info.delegators.keys_as_vector().get(index).unwrap();
+            let delegator_info = reviewer_info.delegators.values_as_vector().get(index).unwrap();
+            require!(delegator_info.deposit_info.deposit_amount == 0, "Delegators are not unbonded");
+            require!(
+                delegator_info.refunding_amount == 0 || env::block_timestamp_ms() - delegator_info.refunding_start_timestamp >= REFUNDING_PERIOD,
+                "Refunds are still unbonding"
+            );
+
+            let initial_storage_usage = env::storage_usage();
+            reviewer_info.delegators.remove(&delegator_id);
+            if let Err(err) = self.internal_try_update_storage_usage(&delegator_id, initial_storage_usage) {
+                log!("[remove_reviewer] {}", err);
+            }
+            if delegator_info.refunding_amount > 0 {
+                self.internal_add_exit_refund(&delegator_id, delegator_info.refunding_amount);
+            }
+            count += 1;
+        }
+
+        let remaining = reviewer_info.delegators.len();
+        if remaining > 0 {
+            self.reviewers.insert(&reviewer_id, &reviewer_info);
+            log!("[remove_reviewer] reviewer_id: {}, count: {}, remaining: {}", reviewer_id, count, remaining);
+            return remaining;
+        }
+
+        self.treasury += reviewer_info.pending_amount;
+        let initial_storage_usage = env::storage_usage();
+        self.reviewers.remove(&reviewer_id);
+        if let Err(err) = self.internal_try_update_storage_usage(&reviewer_id, initial_storage_usage) {
+            log!("[remove_reviewer] {}", err);
+        }
+        log!("[remove_reviewer] reviewer_id: {}, treasury_amount: {}", reviewer_id, reviewer_info.pending_amount);
+        return 0;
+    }
+
+    /// Transfers the refunds held for the caller since the reviewers they delegated to were removed,
+    /// or since a subscription refund couldn't be transferred nor credited
+    pub fn claim_exit_refund(&mut self) -> Promise {
+        let account_id = env::predecessor_account_id();
+        let amount = self.exit_refunds.get(&account_id).expect("No exit refund");
+
+        let initial_storage_usage = env::storage_usage();
+        self.exit_refunds.remove(&account_id);
+        if let Err(err) = self.internal_try_update_storage_usage(&account_id, initial_storage_usage) {
+            log!("[claim_exit_refund] {}", err);
+        }
+
+        log!("[claim_exit_refund] account_id: {}, amount: {}", account_id, amount);
+        return ext_ft_core::ext(self.token_id.clone())
+            .with_attached_deposit(NearToken::from_yoctonear(1))
+            .with_static_gas(Gas::from_tgas(20))
+            .ft_transfer(account_id.clone(), U128(amount), None)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(Gas::from_tgas(10))
+                    .callback_claim_exit_refund(&account_id, amount)
+            );
+    }
+
+    #[private]
+    pub fn callback_claim_exit_refund(&mut self, account_id: &AccountId, amount: Balance) {
+        const REFUND_TRANSFER_PROMISE_INDEX: u64 = 0;
+        if let PromiseResult::Successful(_) = env::promise_result(REFUND_TRANSFER_PROMISE_INDEX) {
+            return;
+        }
+
+        log!("[callback_claim_exit_refund] transfer failed, account_id: {}", account_id);
+        self.internal_add_exit_refund(account_id, amount);
+    }
+
+    pub fn get_exit_refund(&self, account_id: AccountId) -> U128 {
+        return U128(self.exit_refunds.get(&account_id).unwrap_or(0));
+    }
+
+    pub fn get_reviewer_exit_timestamp(&self, reviewer_id: AccountId) -> Option<u64> {
+        return self.reviewers.get(&reviewer_id).and_then(|reviewer_info| reviewer_info.exit_timestamp);
+    }
+}
@@ -0,0 +1,193 @@
+use near_sdk::collections::UnorderedMap;
+use near_sdk::{assert_one_yocto, CurveType, PublicKey};
+
+use crate::*;
+
+const MAX_SIGNER_COUNT: u64 = 10;
+
+#[near(serializers = [borsh, json])]
+pub struct SignerKey {
+    pub added_at: u64,
+    /// Set when the key is rotated out, it stays valid until this timestamp in milliseconds
+    pub expires_at: Option<u64>,
+}
+
+impl SignerKey {
+    pub fn is_active(&self) -> bool {
+        return self.expires_at.is_none_or(|expires_at| env::block_timestamp_ms() < expires_at);
+    }
+}
+
+#[near(serializers = [json])]
+pub struct SignerView {
+    pub public_key: PublicKey,
+    pub added_at: u64,
+    pub expires_at: Option<u64>,
+}
+
+/// Signature of a payload by one of the signers, `signature` is formatted as `ed25519:<base58>`
+#[near(serializers = [json])]
+pub struct PayloadSignature {
+    pub public_key: PublicKey,
+    pub signature: String,
+}
+
+#[near(serializers = [json])]
+pub struct SignerThreshold {
+    pub threshold: u8,
+    pub high_value_amount: U128,
+}
+
+pub(crate) fn new_signers(public_key: Vec<u8>) -> UnorderedMap<Vec<u8>, SignerKey> {
+    let mut signers = UnorderedMap::new(b"k".to_vec());
+    signers.insert(&public_key, &SignerKey {
+        added_at: env::block_timestamp_ms(),
+        expires_at: None,
+    });
+    return signers;
+}
+
+/// The first byte of a public key indicates the curve, signers are stored without it
+fn ed25519_key_bytes(public_key: &PublicKey) -> Vec<u8> {
+    require!(public_key.curve_type() == CurveType::ED25519, "Only ed25519 keys are supported");
+    return public_key.as_bytes()[1..].to_vec();
+}
+
+fn parse_signature(signature: &str) -> Result<[u8; 64], String> {
+    let encoded = signature.strip_prefix("ed25519:").ok_or("Signature must start with ed25519:")?;
+    let bytes = bs58::decode(encoded).into_vec().map_err(|err| err.to_string())?;
+    return bytes.try_into().map_err(|_| "Signature must be 64 bytes".to_string());
+}
+
+impl TicleCore {
+    /// Checks that enough active signers signed `message`, one valid signature is enough below
+    /// the high value amount, `signer_threshold` distinct ones are required at or above it
+    pub(crate) fn internal_verify_signatures(&self, message: &[u8], signatures: &[PayloadSignature], amount: Balance) -> Result<(), String> {
+        let required_count = if amount >= self.high_value_amount { self.signer_threshold as usize } else { 1 };
+
+        let mut signer_keys: Vec<Vec<u8>> = Vec::new();
+        for payload_signature in signatures {
+            let public_key = ed25519_key_bytes(&payload_signature.public_key);
+            if signer_keys.contains(&public_key) {
+                return Err("Duplicated signer".to_string());
+            }
+
+            let signer = self.signers.get(&public_key).ok_or("Unknown signer")?;
+            if !signer.is_active() {
+                return Err("Signer key expired".to_string());
+            }
+
+            let signature = parse_signature(&payload_signature.signature)?;
+            if !env::ed25519_verify(&signature, message, public_key.as_slice().try_into().unwrap()) {
+                return Err("Invalid signature".to_string());
+            }
+            signer_keys.push(public_key);
+        }
+
+        if signer_keys.len() < required_count {
+            return Err(format!("Requires {} signatures, got {}", required_count, signer_keys.len()));
+        }
+        return Ok(());
+    }
+
+    fn active_signer_count(&self) -> usize {
+        return self.signers.values().filter(|signer| signer.is_active()).count();
+    }
+
+    fn internal_add_signer(&mut self, public_key: Vec<u8>) {
+        require!(self.signers.get(&public_key).is_none(), "Signer already exists");
+        require!(self.signers.len() < MAX_SIGNER_COUNT, "Max signer count reached");
+        self.signers.insert(&public_key, &SignerKey {
+            added_at: env::block_timestamp_ms(),
+            expires_at: None,
+        });
+    }
+}
+
+#[near]
+impl TicleCore {
+    #[payable]
+    pub fn add_signer(&mut self, public_key: PublicKey) {
+        assert_one_yocto();
+        self.assert_owner();
+        self.internal_add_signer(ed25519_key_bytes(&public_key));
+        log!("[add_signer] public_key: {:?}", public_key);
+    }
+
+    /// Replaces `old_public_key` with `new_public_key`, both keys are valid during `grace_period` milliseconds
+    #[payable]
+    pub fn rotate_signer(&mut self, old_public_key: PublicKey, new_public_key: PublicKey, grace_period: u64) {
+        assert_one_yocto();
+        self.assert_owner();
+
+        let old_key = ed25519_key_bytes(&old_public_key);
+        let mut old_signer = self.signers.get(&old_key).expect("Signer not found");
+        require!(old_signer.expires_at.is_none(), "Signer is already rotated");
+
+        // Expired keys are dropped so rotations don't fill up the signer slots
+        for (public_key, signer) in self.signers.to_vec() {
+            if !signer.is_active() {
+                self.signers.remove(&public_key);
+            }
+        }
+        self.internal_add_signer(ed25519_key_bytes(&new_public_key));
+
+        old_signer.expires_at = Some(env::block_timestamp_ms() + grace_period);
+        self.signers.insert(&old_key, &old_signer);
+        log!("[rotate_signer] old_public_key: {:?}, new_public_key: {:?}", old_public_key, new_public_key);
+    }
+
+    /// Revokes a key immediately, e.g. when it leaked
+    #[payable]
+    pub fn remove_signer(&mut self, public_key: PublicKey) {
+        assert_one_yocto();
+        self.assert_owner();
+
+        self.signers.remove(&ed25519_key_bytes(&public_key)).expect("Signer not found");
+        require!(self.active_signer_count() >= self.signer_threshold as usize, "Active signers would fall below the threshold");
+        log!("[remove_signer] public_key: {:?}", public_key);
+    }
+
+    /// Payloads worth `high_value_amount` or more need `threshold` distinct signatures
+    #[payable]
+    pub fn set_signer_threshold(&mut self, threshold: u8, high_value_amount: U128) {
+        assert_one_yocto();
+        self.assert_owner();
+        require!(threshold > 0, "Threshold must be positive");
+        require!(threshold as usize <= self.active_signer_count(), "Threshold exceeds the active signer count");
+
+        self.signer_threshold = threshold;
+        self.high_value_amount = high_value_amount.into();
+    }
+
+    pub fn get_signers(&self) -> Vec<SignerView> {
+        return self.signers
+            .iter()
+            .map(|(public_key, signer)| SignerView {
+                public_key: PublicKey::from_parts(CurveType::ED25519, public_key).unwrap(),
+                added_at: signer.added_at,
+                expires_at: signer.expires_at,
+            })
+            .collect();
+    }
+
+    pub fn get_active_signers(&self) -> Vec<SignerView> {
+        return self.get_signers()
+            .into_iter()
+            .filter(|signer| signer.expires_at.is_none_or(|expires_at| env::block_timestamp_ms() < expires_at))
+            .collect();
+    }
+
+    pub fn get_signer_threshold(&self) -> SignerThreshold {
+        return SignerThreshold {
+            threshold: self.signer_threshold,
+            high_value_amount: self.high_value_amount.into(),
+        };
+    }
+
+    /// Whether `signatures` authorize a payload worth `amount`
+    pub fn verify_signatures(&self, message: String, signatures: Vec<PayloadSignature>, amount: U128) -> bool {
+        return self.internal_verify_signatures(message.as_bytes(), &signatures, amount.into()).is_ok();
+    }
+}
+
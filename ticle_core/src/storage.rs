@@ -0,0 +1,160 @@
+use near_contract_standards::storage_management::{StorageBalance, StorageBalanceBounds, StorageManagement};
+use near_sdk::assert_one_yocto;
+
+use crate::*;
+
+#[near(serializers = [borsh])]
+pub struct AccountStorage {
+    deposit_amount: NearToken,
+    used_bytes: u64,
+}
+
+impl TicleCore {
+    pub(crate) fn measure_account_storage_usage(&mut self) {
+        let initial_storage_usage = env::storage_usage();
+        let tmp_account_id: AccountId = "a".repeat(64).parse().unwrap();
+        self.accounts.insert(&tmp_account_id, &AccountStorage {
+            deposit_amount: NearToken::from_yoctonear(0),
+            used_bytes: 0,
+        });
+        self.account_storage_usage = env::storage_usage() - initial_storage_usage;
+        self.accounts.remove(&tmp_account_id);
+    }
+
+    pub(crate) fn assert_registered(&self, account_id: &AccountId) {
+        require!(self.accounts.contains_key(account_id), format!("The account {} is not registered", account_id));
+    }
+
+    /// Attributes the storage difference since `initial_storage_usage` to `account_id`.
+    /// A positive difference is charged against the account's storage deposit, a negative one is refunded to it.
+    pub(crate) fn internal_update_storage_usage(&mut self, account_id: &AccountId, initial_storage_usage: u64) {
+        self.internal_try_update_storage_usage(account_id, initial_storage_usage).unwrap_or_else(|err| env::panic_str(&err));
+    }
+
+    /// Same as `internal_update_storage_usage`, but leaves the account untouched and returns an error
+    /// when the account is not registered or its deposit doesn't cover the storage.
+    pub(crate) fn internal_try_update_storage_usage(&mut self, account_id: &AccountId, initial_storage_usage: u64) -> Result<(), String> {
+        let storage_usage = env::storage_usage();
+        return self.internal_try_charge_storage(account_id, storage_usage.saturating_sub(initial_storage_usage), initial_storage_usage.saturating_sub(storage_usage));
+    }
+
+    /// Charges `added_bytes` to `account_id` and releases `released_bytes` of it, for storage that isn't measured
+    /// around the change itself. Fails like `internal_try_update_storage_usage`.
+    pub(crate) fn internal_try_charge_storage(&mut self, account_id: &AccountId, added_bytes: u64, released_bytes: u64) -> Result<(), String> {
+        let mut account_storage = self.accounts.get(account_id).ok_or_else(|| format!("The account {} is not registered", account_id))?;
+        account_storage.used_bytes = (account_storage.used_bytes + added_bytes).saturating_sub(released_bytes);
+
+        let required_amount = self.storage_cost(account_storage.used_bytes);
+        if account_storage.deposit_amount < required_amount {
+            return Err(format!("Not enough storage deposit for {}, required: {}", account_id, required_amount));
+        }
+
+        self.accounts.insert(account_id, &account_storage);
+        return Ok(());
+    }
+
+    fn storage_cost(&self, used_bytes: u64) -> NearToken {
+        return env::storage_byte_cost().saturating_mul((self.account_storage_usage + used_bytes) as u128);
+    }
+
+    fn internal_storage_balance_of(&self, account_id: &AccountId) -> Option<StorageBalance> {
+        let account_storage = self.accounts.get(account_id)?;
+        return Some(StorageBalance {
+            total: account_storage.deposit_amount,
+            available: account_storage.deposit_amount.saturating_sub(self.storage_cost(account_storage.used_bytes)),
+        });
+    }
+}
+
+#[near]
+impl StorageManagement for TicleCore {
+    #[payable]
+    fn storage_deposit(&mut self, account_id: Option<AccountId>, registration_only: Option<bool>) -> StorageBalance {
+        let amount = env::attached_deposit();
+        let account_id = account_id.unwrap_or_else(env::predecessor_account_id);
+        let registration_only = registration_only.unwrap_or(false);
+        let min_balance = self.storage_balance_bounds().min;
+
+        match self.accounts.get(&account_id) {
+            Some(mut account_storage) => {
+                if registration_only {
+                    log!("The account is already registered, refunding the deposit");
+                    if amount > NearToken::from_yoctonear(0) {
+                        Promise::new(env::predecessor_account_id()).transfer(amount);
+                    }
+                } else {
+                    account_storage.deposit_amount = account_storage.deposit_amount.saturating_add(amount);
+                    self.accounts.insert(&account_id, &account_storage);
+                }
+            }
+            None => {
+                require!(amount >= min_balance, "The attached deposit is less than the minimum storage balance");
+
+                let deposit_amount = if registration_only { min_balance } else { amount };
+                self.accounts.insert(&account_id, &AccountStorage {
+                    deposit_amount,
+                    used_bytes: 0,
+                });
+
+                let refund = amount.saturating_sub(deposit_amount);
+                if refund > NearToken::from_yoctonear(0) {
+                    Promise::new(env::predecessor_account_id()).transfer(refund);
+                }
+            }
+        }
+
+        return self.internal_storage_balance_of(&account_id).unwrap();
+    }
+
+    #[payable]
+    fn storage_withdraw(&mut self, amount: Option<NearToken>) -> StorageBalance {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let storage_balance = self.internal_storage_balance_of(&account_id).unwrap_or_else(|| env::panic_str(&format!("The account {} is not registered", account_id)));
+
+        let amount = amount.unwrap_or(storage_balance.available);
+        require!(amount <= storage_balance.available, "The amount is greater than the available storage balance");
+        if amount == NearToken::from_yoctonear(0) {
+            return storage_balance;
+        }
+
+        let mut account_storage = self.accounts.get(&account_id).unwrap();
+        account_storage.deposit_amount = account_storage.deposit_amount.saturating_sub(amount);
+        self.accounts.insert(&account_id, &account_storage);
+
+        Promise::new(account_id.clone()).transfer(amount);
+        return self.internal_storage_balance_of(&account_id).unwrap();
+    }
+
+    // Records owned by the account live inside VAPIs and reviewers, so they can't be dropped by `force`
+    #[allow(unused_variables)]
+    #[payable]
+    fn storage_unregister(&mut self, force: Option<bool>) -> bool {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let account_storage = match self.accounts.get(&account_id) {
+            Some(account_storage) => account_storage,
+            None => {
+                log!("The account {} is not registered", account_id);
+                return false;
+            }
+        };
+
+        require!(account_storage.used_bytes == 0, "Can't unregister the account with active records");
+
+        self.accounts.remove(&account_id);
+        Promise::new(account_id).transfer(account_storage.deposit_amount.saturating_add(NearToken::from_yoctonear(1)));
+        return true;
+    }
+
+    fn storage_balance_bounds(&self) -> StorageBalanceBounds {
+        return StorageBalanceBounds {
+            min: self.storage_cost(0),
+            max: None,
+        };
+    }
+
+    fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance> {
+        return self.internal_storage_balance_of(&account_id);
+    }
+}
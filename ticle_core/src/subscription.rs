@@ -0,0 +1,198 @@
+use crate::*;
+
+/// Priced per period by the coder of `vapi_id`, `quota` is the number of calls per period enforced by the gateway
+#[near(serializers = [borsh, json])]
+pub struct SubscriptionPlan {
+    pub vapi_id: String,
+    pub price: Balance,
+    pub period_duration: u64,
+    pub quota: u64,
+    pub active: bool,
+}
+
+/// Timestamps are in milliseconds, `escrow_amount` is the paid revenue of the periods after `settled_until`
+#[near(serializers = [borsh, json])]
+pub struct Subscription {
+    pub paid_until: u64,
+    pub settled_until: u64,
+    pub escrow_amount: Balance,
+}
+
+impl TicleCore {
+    /// Subscribes or renews for as many periods as `amount` pays for, renewals extend the paid time
+    pub(crate) fn internal_subscribe(&mut self, consumer_id: &AccountId, plan_id: u64, amount: Balance) -> Result<(), String> {
+        log!("[internal_subscribe] consumer_id: {}, plan_id: {}, amount: {}", consumer_id, plan_id, amount);
+        let plan = self.subscription_plans.get(&plan_id).ok_or("Subscription plan not found")?;
+        if !plan.active {
+            return Err("Subscription plan is not active".to_string());
+        }
+        if amount == 0 || !amount.is_multiple_of(plan.price) {
+            return Err(format!("Amount must be a multiple of the price {}", plan.price));
+        }
+        let period_count = (amount / plan.price) as u64;
+
+        let timestamp = env::block_timestamp_ms();
+        let key = (consumer_id.clone(), plan_id);
+        let is_new_subscription = !self.subscriptions.contains_key(&key);
+        let mut subscription = self.subscriptions.get(&key).unwrap_or(Subscription {
+            paid_until: timestamp,
+            settled_until: timestamp,
+            escrow_amount: 0,
+        });
+
+        // A lapsed subscription restarts now, the periods it paid for are settled first
+        if subscription.paid_until < timestamp {
+            let settled_amount = self.internal_settle_subscription(&plan, &mut subscription, timestamp);
+            self.internal_distribute_subscription_revenue(&plan, settled_amount);
+            subscription.paid_until = timestamp;
+            subscription.settled_until = timestamp;
+        }
+        subscription.paid_until += period_count * plan.period_duration;
+        subscription.escrow_amount += amount;
+
+        let initial_storage_usage = env::storage_usage();
+        self.subscriptions.insert(&key, &subscription);
+        if let Err(err) = self.internal_try_update_storage_usage(consumer_id, initial_storage_usage) {
+            if is_new_subscription {
+                self.subscriptions.remove(&key);
+            }
+            return Err(err);
+        }
+        return Ok(());
+    }
+
+    /// Releases the revenue of the periods fully elapsed by `timestamp` from the escrow
+    fn internal_settle_subscription(&mut self, plan: &SubscriptionPlan, subscription: &mut Subscription, timestamp: u64) -> Balance {
+        let settle_until = timestamp.min(subscription.paid_until);
+        if settle_until <= subscription.settled_until {
+            return 0;
+        }
+
+        let period_count = (settle_until - subscription.settled_until) / plan.period_duration;
+        let settled_amount = plan.price * period_count as u128;
+        subscription.settled_until += period_count * plan.period_duration;
+        subscription.escrow_amount -= settled_amount;
+        return settled_amount;
+    }
+
+    /// Routes subscription revenue through the settlement split
+    fn internal_distribute_subscription_revenue(&mut self, plan: &SubscriptionPlan, amount: Balance) -> Option<Promise> {
+        if amount == 0 {
+            return None;
+        }
+        let distribution = self.internal_distribute(std::slice::from_ref(&plan.vapi_id), &[amount]);
+        return Some(self.internal_burn(distribution.burn_amount));
+    }
+}
+
+#[near]
+impl TicleCore {
+    pub fn create_subscription_plan(&mut self, vapi_id: String, price: U128, period_duration: u64, quota: u64) -> u64 {
+        let coder_id = env::predecessor_account_id();
+        let vapi = self.vapis.get(&vapi_id).expect("Vertical API not found");
+        require!(vapi.coder_info.account_id == coder_id, "Only coder can create subscription plans");
+        require!(price.0 > 0 && period_duration > 0, "Price and period duration must be positive");
+
+        let plan_id = self.subscription_plan_count;
+        let initial_storage_usage = env::storage_usage();
+        self.subscription_plans.insert(&plan_id, &SubscriptionPlan {
+            vapi_id,
+            price: price.into(),
+            period_duration,
+            quota,
+            active: true,
+        });
+        self.subscription_plan_count += 1;
+        self.internal_update_storage_usage(&coder_id, initial_storage_usage);
+
+        log!("[create_subscription_plan] plan_id: {}", plan_id);
+        return plan_id;
+    }
+
+    /// Stops new subscriptions and renewals, running subscriptions last until their paid time ends
+    pub fn deactivate_subscription_plan(&mut self, plan_id: u64) {
+        let mut plan = self.subscription_plans.get(&plan_id).expect("Subscription plan not found");
+        let vapi = self.vapis.get(&plan.vapi_id).expect("Vertical API not found");
+        require!(vapi.coder_info.account_id == env::predecessor_account_id(), "Only coder can deactivate subscription plans");
+
+        plan.active = false;
+        self.subscription_plans.insert(&plan_id, &plan);
+    }
+
+    /// Distributes the revenue of the elapsed periods of a subscription, callable by anyone
+    pub fn settle_subscription(&mut self, consumer_id: AccountId, plan_id: u64) -> Promise {
+        let plan = self.subscription_plans.get(&plan_id).expect("Subscription plan not found");
+        let key = (consumer_id, plan_id);
+        let mut subscription = self.subscriptions.get(&key).expect("Subscription not found");
+
+        let settled_amount = self.internal_settle_subscription(&plan, &mut subscription, env::block_timestamp_ms());
+        require!(settled_amount > 0, "Nothing to settle");
+        self.subscriptions.insert(&key, &subscription);
+
+        log!("[settle_subscription] plan_id: {}, settled_amount: {}", plan_id, settled_amount);
+        return self.internal_distribute_subscription_revenue(&plan, settled_amount).unwrap();
+    }
+
+    /// Ends the caller's subscription, the current period is charged pro rata and the rest of the escrow is refunded
+    pub fn cancel_subscription(&mut self, plan_id: u64) -> Promise {
+        let consumer_id = env::predecessor_account_id();
+        let plan = self.subscription_plans.get(&plan_id).expect("Subscription plan not found");
+        let key = (consumer_id.clone(), plan_id);
+        let mut subscription = self.subscriptions.get(&key).expect("Subscription not found");
+
+        let timestamp = env::block_timestamp_ms();
+        let mut settled_amount = self.internal_settle_subscription(&plan, &mut subscription, timestamp);
+        if timestamp < subscription.paid_until {
+            let prorated_amount = mul_div(plan.price, (timestamp - subscription.settled_until) as u128, plan.period_duration as u128).expect("Prorated amount overflow");
+            subscription.escrow_amount -= prorated_amount;
+            settled_amount += prorated_amount;
+        }
+        let refund_amount = subscription.escrow_amount;
+
+        let initial_storage_usage = env::storage_usage();
+        self.subscriptions.remove(&key);
+        self.internal_update_storage_usage(&consumer_id, initial_storage_usage);
+        self.internal_distribute_subscription_revenue(&plan, settled_amount);
+
+        log!("[cancel_subscription] plan_id: {}, settled_amount: {}, refund_amount: {}", plan_id, settled_amount, refund_amount);
+        if refund_amount == 0 {
+            return Promise::new(consumer_id);
+        }
+        return ext_ft_core::ext(self.token_id.clone())
+            .with_attached_deposit(NearToken::from_yoctonear(1))
+            .with_static_gas(Gas::from_tgas(20))
+            .ft_transfer(consumer_id.clone(), U128(refund_amount), None)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(Gas::from_tgas(20))
+                    .callback_cancel_subscription(&consumer_id, refund_amount)
+            );
+    }
+
+    /// A failed refund is credited to the consumer balance, from where it can be withdrawn. When the consumer
+    /// can't be funded, e.g. the account was unregistered meanwhile, it's held as an exit refund instead.
+    #[private]
+    pub fn callback_cancel_subscription(&mut self, consumer_id: &AccountId, refund_amount: Balance) {
+        const REFUND_TRANSFER_PROMISE_INDEX: u64 = 0;
+        if let PromiseResult::Failed = env::promise_result(REFUND_TRANSFER_PROMISE_INDEX) {
+            log!("[callback_cancel_subscription] refund failed, consumer_id: {}", consumer_id);
+            if let Err(err) = self.internal_fund_consumer(consumer_id, refund_amount) {
+                log!("[callback_cancel_subscription] {}", err);
+                self.internal_add_exit_refund(consumer_id, refund_amount);
+            }
+        }
+    }
+
+    pub fn get_subscription_plan(&self, plan_id: u64) -> Option<SubscriptionPlan> {
+        return self.subscription_plans.get(&plan_id);
+    }
+
+    pub fn get_subscription(&self, consumer_id: AccountId, plan_id: u64) -> Option<Subscription> {
+        return self.subscriptions.get(&(consumer_id, plan_id));
+    }
+
+    pub fn is_subscription_active(&self, consumer_id: AccountId, plan_id: u64) -> bool {
+        return self.subscriptions.get(&(consumer_id, plan_id)).is_some_and(|subscription| env::block_timestamp_ms() < subscription.paid_until);
+    }
+}
+
@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+
+use near_contract_standards::non_fungible_token::approval::NonFungibleTokenApproval;
+use near_contract_standards::non_fungible_token::core::{NonFungibleTokenCore, NonFungibleTokenResolver};
+use near_contract_standards::non_fungible_token::enumeration::NonFungibleTokenEnumeration;
+use near_contract_standards::non_fungible_token::events::NftMint;
+use near_contract_standards::non_fungible_token::metadata::{NFTContractMetadata, NonFungibleTokenMetadataProvider, TokenMetadata, NFT_METADATA_SPEC};
+use near_contract_standards::non_fungible_token::{NonFungibleToken, Token, TokenId};
+use near_sdk::assert_one_yocto;
+use near_sdk::collections::LazyOption;
+
+use crate::*;
+
+const GAS_FOR_RESOLVE_TRANSFER: Gas = Gas::from_tgas(20);
+const GAS_FOR_NFT_TRANSFER_CALL: Gas = Gas::from_tgas(50);
+
+#[ext_contract(ext_nft_receiver)]
+pub trait NonFungibleTokenReceiver {
+    fn nft_on_transfer(&mut self, sender_id: AccountId, previous_owner_id: AccountId, token_id: TokenId, msg: String) -> PromiseOrValue<bool>;
+}
+
+pub(crate) fn new_vapi_tokens(owner_id: AccountId) -> (NonFungibleToken, LazyOption<NFTContractMetadata>) {
+    let vapi_tokens = NonFungibleToken::new(b"o".to_vec(), owner_id, Some(b"m".to_vec()), Some(b"e".to_vec()), Some(b"w".to_vec()));
+    let metadata = NFTContractMetadata {
+        spec: NFT_METADATA_SPEC.to_string(),
+        name: "Ticle Vertical API".to_string(),
+        symbol: "VAPI".to_string(),
+        icon: None,
+        base_uri: None,
+        reference: None,
+        reference_hash: None,
+    };
+    return (vapi_tokens, LazyOption::new(b"t".to_vec(), Some(&metadata)));
+}
+
+/// Refunds the approvals dropped by a transfer to the owner who attached the deposit for them, priced as `nft_approve` charges them
+pub(crate) fn refund_approved_account_ids(owner_id: AccountId, approved_account_ids: &HashMap<AccountId, u64>) {
+    let released_bytes: u64 = approved_account_ids.keys().map(|account_id| account_id.as_str().len() as u64 + 4 + 8).sum();
+    if released_bytes > 0 {
+        Promise::new(owner_id).transfer(env::storage_byte_cost().saturating_mul(released_bytes as u128));
+    }
+}
+
+impl TicleCore {
+    /// Mints the token of a new VAPI and returns the storage of its records, the caller accounts for it
+    pub(crate) fn internal_mint_vapi_token(&mut self, vapi_id: &str, coder_id: &AccountId) -> u64 {
+        let initial_storage_usage = env::storage_usage();
+        let metadata = TokenMetadata {
+            title: Some(vapi_id.to_string()),
+            ..Default::default()
+        };
+        self.vapi_tokens.internal_mint_with_refund(vapi_id.to_string(), coder_id.clone(), Some(metadata), None);
+        NftMint { owner_id: coder_id, token_ids: &[vapi_id], memo: None }.emit();
+        return env::storage_usage() - initial_storage_usage;
+    }
+
+    /// Stores the VAPI record for `new_coder_id`. The storage of the record and of the token measured at mint
+    /// is released to the previous coder and charged to the new one, approvals are paid by whoever set them.
+    /// Rewards accrued by the previous coder aren't handed over, they stay claimable by the previous coder.
+    /// A pending ownership transfer proposal doesn't survive the change of hands.
+    fn internal_assign_vapi(&mut self, vapi_id: &String, mut vapi: VAPIInfo, new_coder_id: &AccountId) -> Result<(), String> {
+        let previous_coder_id = vapi.coder_info.account_id.clone();
+        let previous_storage_usage = env::storage_usage();
+        self.vapis.remove(vapi_id);
+        let released_bytes = previous_storage_usage - env::storage_usage() + vapi.token_storage_usage;
+
+        vapi.keep_coder_rewards();
+        vapi.pending_transfer = None;
+        vapi.coder_info.account_id = new_coder_id.clone();
+        let initial_storage_usage = env::storage_usage();
+        self.vapis.insert(vapi_id, &vapi);
+        let added_bytes = env::storage_usage() - initial_storage_usage + vapi.token_storage_usage;
+
+        self.internal_try_charge_storage(&previous_coder_id, 0, released_bytes)?;
+        return self.internal_try_charge_storage(new_coder_id, added_bytes, 0);
+    }
+
+    /// Moves the token and the coder ownership of a VAPI, `sender_id` is the coder or an approved account.
+    /// Backs both the NEP-171 transfers and `accept_ownership_transfer`.
+    pub(crate) fn internal_transfer_vapi(&mut self, sender_id: &AccountId, receiver_id: &AccountId, vapi_id: &String, approval_id: Option<u64>, memo: Option<String>) -> (AccountId, Option<HashMap<AccountId, u64>>) {
+        self.assert_registered(receiver_id);
+        let vapi = self.vapis.get(vapi_id).expect("Vertical API not found");
+        let result = self.vapi_tokens.internal_transfer(sender_id, receiver_id, vapi_id, approval_id, memo);
+        self.internal_assign_vapi(vapi_id, vapi, receiver_id).unwrap_or_else(|err| env::panic_str(&err));
+        return result;
+    }
+}
+
+#[near]
+impl NonFungibleTokenCore for TicleCore {
+    #[payable]
+    fn nft_transfer(&mut self, receiver_id: AccountId, token_id: TokenId, approval_id: Option<u64>, memo: Option<String>) {
+        assert_one_yocto();
+        let (previous_owner_id, approved_account_ids) = self.internal_transfer_vapi(&env::predecessor_account_id(), &receiver_id, &token_id, approval_id, memo);
+        if let Some(approved_account_ids) = approved_account_ids {
+            refund_approved_account_ids(previous_owner_id, &approved_account_ids);
+        }
+    }
+
+    #[payable]
+    fn nft_transfer_call(&mut self, receiver_id: AccountId, token_id: TokenId, approval_id: Option<u64>, memo: Option<String>, msg: String) -> PromiseOrValue<bool> {
+        assert_one_yocto();
+        require!(env::prepaid_gas() > GAS_FOR_NFT_TRANSFER_CALL, "More gas is required");
+        let sender_id = env::predecessor_account_id();
+        let (previous_owner_id, approved_account_ids) = self.internal_transfer_vapi(&sender_id, &receiver_id, &token_id, approval_id, memo);
+
+        return ext_nft_receiver::ext(receiver_id.clone())
+            .with_static_gas(env::prepaid_gas().saturating_sub(GAS_FOR_NFT_TRANSFER_CALL))
+            .nft_on_transfer(sender_id, previous_owner_id.clone(), token_id.clone(), msg)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_RESOLVE_TRANSFER)
+                    .nft_resolve_transfer(previous_owner_id, receiver_id, token_id, approved_account_ids)
+            )
+            .into();
+    }
+
+    fn nft_token(&self, token_id: TokenId) -> Option<Token> {
+        return self.vapi_tokens.nft_token(token_id);
+    }
+}
+
+#[near]
+impl NonFungibleTokenResolver for TicleCore {
+    #[private]
+    fn nft_resolve_transfer(&mut self, previous_owner_id: AccountId, receiver_id: AccountId, token_id: TokenId, approved_account_ids: Option<HashMap<AccountId, u64>>) -> bool {
+        let is_transferred = self.vapi_tokens.nft_resolve_transfer(previous_owner_id.clone(), receiver_id, token_id.clone(), approved_account_ids);
+        if is_transferred {
+            return true;
+        }
+
+        // The token is back with the previous owner, so is the VAPI. A storage deposit the previous owner
+        // drained meanwhile is covered by the contract, the callback must not fail.
+        let vapi = self.vapis.get(&token_id).expect("Vertical API not found");
+        if let Err(err) = self.internal_assign_vapi(&token_id, vapi, &previous_owner_id) {
+            log!("[nft_resolve_transfer] {}", err);
+        }
+        return false;
+    }
+}
+
+#[near]
+impl NonFungibleTokenApproval for TicleCore {
+    #[payable]
+    fn nft_approve(&mut self, token_id: TokenId, account_id: AccountId, msg: Option<String>) -> Option<Promise> {
+        return self.vapi_tokens.nft_approve(token_id, account_id, msg);
+    }
+
+    #[payable]
+    fn nft_revoke(&mut self, token_id: TokenId, account_id: AccountId) {
+        self.vapi_tokens.nft_revoke(token_id, account_id);
+    }
+
+    #[payable]
+    fn nft_revoke_all(&mut self, token_id: TokenId) {
+        self.vapi_tokens.nft_revoke_all(token_id);
+    }
+
+    fn nft_is_approved(&self, token_id: TokenId, approved_account_id: AccountId, approval_id: Option<u64>) -> bool {
+        return self.vapi_tokens.nft_is_approved(token_id, approved_account_id, approval_id);
+    }
+}
+
+#[near]
+impl NonFungibleTokenEnumeration for TicleCore {
+    fn nft_total_supply(&self) -> U128 {
+        return self.vapi_tokens.nft_total_supply();
+    }
+
+    fn nft_tokens(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<Token> {
+        return self.vapi_tokens.nft_tokens(from_index, limit);
+    }
+
+    fn nft_supply_for_owner(&self, account_id: AccountId) -> U128 {
+        return self.vapi_tokens.nft_supply_for_owner(account_id);
+    }
+
+    fn nft_tokens_for_owner(&self, account_id: AccountId, from_index: Option<U128>, limit: Option<u64>) -> Vec<Token> {
+        return self.vapi_tokens.nft_tokens_for_owner(account_id, from_index, limit);
+    }
+}
+
+#[near]
+impl NonFungibleTokenMetadataProvider for TicleCore {
+    fn nft_metadata(&self) -> NFTContractMetadata {
+        return self.vapi_token_metadata.get().unwrap();
+    }
+}
@@ -1,15 +1,33 @@
 use near_sdk::json_types::U128;
 use near_workspaces::{types::NearToken, Account, AccountId, Contract, DevNetwork, Worker};
 use near_contract_standards::fungible_token::metadata::FungibleTokenMetadata;
+use near_contract_standards::storage_management::StorageBalanceBounds;
 
 pub const ONE_YOCTO: NearToken = NearToken::from_yoctonear(1);
 
 pub async fn register_user(contract: &Contract, account_id: &AccountId) -> anyhow::Result<()> {
+    let storage_balance_bounds = contract
+        .view("storage_balance_bounds")
+        .await?
+        .json::<StorageBalanceBounds>()?;
     let res = contract
         .call("storage_deposit")
         .args_json((account_id, Option::<bool>::None))
         .max_gas()
-        .deposit(near_sdk::env::storage_byte_cost().saturating_mul(125))
+        .deposit(storage_balance_bounds.min)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    return Ok(());
+}
+
+pub async fn deposit_storage(contract: &Contract, account_id: &AccountId, amount: NearToken) -> anyhow::Result<()> {
+    let res = contract
+        .call("storage_deposit")
+        .args_json((account_id, Option::<bool>::None))
+        .max_gas()
+        .deposit(amount)
         .transact()
         .await?;
     assert!(res.is_success());
@@ -50,7 +68,7 @@ pub async fn init(
 
     let res = ft_contract
         .call("new")
-        .args_json((ft_contract.id(), initial_balance, token_metadata))
+        .args_json((ft_contract.id(), initial_balance, token_metadata, initial_balance))
         .max_gas()
         .transact()
         .await?;
@@ -58,7 +76,7 @@ pub async fn init(
 
     let users = create_users(worker, vec!["owner"], vec![50]).await?;
 
-    let owner = users.get(0).unwrap().clone();
+    let owner = users.first().unwrap().clone();
     register_user(&ft_contract, owner.id()).await?;
 
     let res = ft_contract
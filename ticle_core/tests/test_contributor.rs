@@ -0,0 +1,112 @@
+#![allow(clippy::needless_return)]
+
+use near_sdk::{json_types::U128, NearToken};
+use serde_json::json;
+use ticle_core::contributor::ContributorInfo;
+
+use crate::common::utils::*;
+pub mod common;
+
+#[tokio::test]
+async fn test_contributor_rewards() -> anyhow::Result<()> {
+    let initial_balance = U128::from(NearToken::from_near(10000).as_yoctonear());
+    let worker = near_workspaces::sandbox().await?;
+    let (ft_contract, owner, core_contract) = init(&worker, initial_balance).await?;
+
+    register_user(&ft_contract, core_contract.id()).await?;
+    deposit_storage(&core_contract, owner.id(), NearToken::from_millinear(100)).await?;
+
+    let users = create_users(&worker, vec!["coder", "alice"], vec![10, 10]).await?;
+    for user in users.iter() {
+        register_user(&ft_contract, user.id()).await?;
+        deposit_storage(&core_contract, user.id(), NearToken::from_millinear(100)).await?;
+    }
+
+    let coder = users.first().unwrap().clone();
+    let alice = users.get(1).unwrap().clone();
+
+    let vapi_id = "test-vapi";
+    let res = coder
+        .call(core_contract.id(), "create_vapi")
+        .args_json(json!({"vapi_id": vapi_id}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    // Only the coder manages the contributors
+    let res = alice
+        .call(core_contract.id(), "set_contributors")
+        .args_json(json!({"vapi_id": vapi_id, "account_ids": [alice.id()], "share_bps": [10000]}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_failure());
+
+    let res = coder
+        .call(core_contract.id(), "set_contributors")
+        .args_json(json!({"vapi_id": vapi_id, "account_ids": [alice.id()], "share_bps": [3000]}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let contributors = core_contract
+        .call("get_vapi_contributors")
+        .args_json(json!({"vapi_id": vapi_id}))
+        .view()
+        .await?
+        .json::<Vec<ContributorInfo>>()?;
+    assert_eq!(contributors.len(), 1);
+    assert_eq!(contributors[0].share_bps, 3000);
+
+    let amount = U128::from(NearToken::from_near(100).as_yoctonear());
+    let res = owner
+        .call(ft_contract.id(), "ft_transfer_call")
+        .args_json((core_contract.id(), amount, Option::<String>::None, json!({ "action": "settlement", "settlement_id": 1, "vapi_ids": [vapi_id], "amounts": [amount] }).to_string()))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+    assert_eq!(res.json::<U128>()?, amount);
+
+    // 60% of the settlement is coder revenue, alice gets 30% of it
+    for (account_id, reward_amount) in [(coder.id(), NearToken::from_near(42)), (alice.id(), NearToken::from_near(18))] {
+        let vapi_reward_amount = core_contract
+            .call("get_vapi_reward_amount")
+            .args_json(json!({"vapi_id": vapi_id, "account_id": account_id}))
+            .view()
+            .await?
+            .json::<u128>()?;
+        assert_eq!(vapi_reward_amount, reward_amount.as_yoctonear());
+    }
+
+    let res = alice
+        .call(core_contract.id(), "claim_vapi_reward")
+        .args_json(json!({"vapi_id": vapi_id}))
+        .max_gas()
+        .transact()
+        .await?;
+    res.logs().iter().for_each(|log| println!("{:?}", log));
+    assert!(res.is_success());
+
+    let alice_balance = ft_contract
+        .call("ft_balance_of")
+        .args_json(json!({"account_id": alice.id()}))
+        .view()
+        .await?
+        .json::<U128>()?;
+    assert_eq!(alice_balance, U128::from(NearToken::from_near(18).as_yoctonear()));
+
+    // The coder's portion is unaffected by the contributor's claim
+    let coder_reward_amount = core_contract
+        .call("get_vapi_reward_amount")
+        .args_json(json!({"vapi_id": vapi_id, "account_id": coder.id()}))
+        .view()
+        .await?
+        .json::<u128>()?;
+    assert_eq!(coder_reward_amount, NearToken::from_near(42).as_yoctonear());
+
+    return Ok(());
+}
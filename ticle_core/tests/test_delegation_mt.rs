@@ -0,0 +1,242 @@
+#![allow(clippy::needless_return)]
+
+use near_contract_standards::storage_management::StorageBalance;
+use near_sdk::{json_types::U128, NearToken};
+use near_workspaces::{Account, Contract};
+use serde_json::json;
+
+use crate::common::utils::*;
+pub mod common;
+
+async fn storage_balance_of(core_contract: &Contract, account: &Account) -> anyhow::Result<StorageBalance> {
+    return Ok(core_contract
+        .call("storage_balance_of")
+        .args_json(json!({"account_id": account.id()}))
+        .view()
+        .await?
+        .json::<Option<StorageBalance>>()?
+        .unwrap());
+}
+
+#[tokio::test]
+async fn test_delegation_transfer() -> anyhow::Result<()> {
+    let initial_balance = U128::from(NearToken::from_near(10000).as_yoctonear());
+    let worker = near_workspaces::sandbox().await?;
+    let (ft_contract, owner, core_contract) = init(&worker, initial_balance).await?;
+
+    register_user(&ft_contract, core_contract.id()).await?;
+    deposit_storage(&core_contract, owner.id(), NearToken::from_millinear(100)).await?;
+
+    let users = create_users(&worker, vec!["alice", "bob", "charlie", "reviewer"], vec![10, 10, 10, 10]).await?;
+    let alice = users.first().unwrap().clone();
+    let bob = users.get(1).unwrap().clone();
+    let charlie = users.get(2).unwrap().clone();
+    let reviewer = users.get(3).unwrap().clone();
+    for user in [&alice, &bob, &reviewer] {
+        deposit_storage(&core_contract, user.id(), NearToken::from_millinear(100)).await?;
+    }
+    for user in [&alice, &bob] {
+        register_user(&ft_contract, user.id()).await?;
+    }
+
+    let res = owner
+        .call(ft_contract.id(), "ft_transfer")
+        .args_json((alice.id(), U128::from(NearToken::from_near(100).as_yoctonear()), "transfer to test account"))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let res = reviewer
+        .call(core_contract.id(), "create_reviewer")
+        .args_json(json!({"reviewer_id": reviewer.id()}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let deposit_amount = U128::from(NearToken::from_near(10).as_yoctonear());
+    let res = alice
+        .call(ft_contract.id(), "ft_transfer_call")
+        .args_json((core_contract.id(), deposit_amount, Option::<String>::None, json!({ "action": "deposit_to_reviewer", "reviewer_id": reviewer.id() }).to_string()))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let alice_balance = core_contract
+        .call("mt_balance_of")
+        .args_json(json!({"account_id": alice.id(), "token_id": reviewer.id()}))
+        .view()
+        .await?
+        .json::<U128>()?;
+    assert_eq!(alice_balance, deposit_amount);
+
+    // The receiver has to be registered to hold the position
+    let transfer_amount = U128::from(NearToken::from_near(4).as_yoctonear());
+    let res = alice
+        .call(core_contract.id(), "mt_transfer")
+        .args_json(json!({"receiver_id": charlie.id(), "token_id": reviewer.id(), "amount": transfer_amount}))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_failure());
+
+    // A registered account not delegating yet gets a new record, charged to its storage deposit
+    let bob_storage_balance = storage_balance_of(&core_contract, &bob).await?;
+    let res = alice
+        .call(core_contract.id(), "mt_transfer")
+        .args_json(json!({"receiver_id": bob.id(), "token_id": reviewer.id(), "amount": transfer_amount}))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    res.logs().iter().for_each(|log| println!("{:?}", log));
+    assert!(res.is_success());
+    assert!(storage_balance_of(&core_contract, &bob).await?.available < bob_storage_balance.available);
+
+    let bob_deposit_amount = U128::from(NearToken::from_near(1).as_yoctonear());
+    let res = alice
+        .call(ft_contract.id(), "ft_transfer")
+        .args_json((bob.id(), bob_deposit_amount, Option::<String>::None))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let res = bob
+        .call(ft_contract.id(), "ft_transfer_call")
+        .args_json((core_contract.id(), bob_deposit_amount, Option::<String>::None, json!({ "action": "deposit_to_reviewer", "reviewer_id": reviewer.id() }).to_string()))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let balances = core_contract
+        .call("mt_batch_balance_of")
+        .args_json(json!({"account_id": bob.id(), "token_ids": [reviewer.id(), alice.id()]}))
+        .view()
+        .await?
+        .json::<Vec<U128>>()?;
+    assert_eq!(balances, vec![U128(transfer_amount.0 + bob_deposit_amount.0), U128(0)]);
+
+    // A receiver without `mt_on_transfer` uses nothing, so the position moves back to Bob
+    let res = bob
+        .call(core_contract.id(), "mt_transfer_call")
+        .args_json(json!({"receiver_id": alice.id(), "token_id": reviewer.id(), "amount": transfer_amount, "msg": ""}))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    res.logs().iter().for_each(|log| println!("{:?}", log));
+    assert!(res.is_success());
+    assert_eq!(res.json::<Vec<U128>>()?, vec![U128(0)]);
+
+    for (account_id, balance) in [(alice.id(), NearToken::from_near(6)), (bob.id(), NearToken::from_near(5))] {
+        let mt_balance = core_contract
+            .call("mt_balance_of")
+            .args_json(json!({"account_id": account_id, "token_id": reviewer.id()}))
+            .view()
+            .await?
+            .json::<U128>()?;
+        assert_eq!(mt_balance, U128::from(balance.as_yoctonear()));
+    }
+
+    let supply = core_contract
+        .call("mt_supply")
+        .args_json(json!({"token_id": reviewer.id()}))
+        .view()
+        .await?
+        .json::<Option<U128>>()?;
+    assert_eq!(supply, Some(U128(deposit_amount.0 + bob_deposit_amount.0)));
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn test_delegation_transfer_call_refund() -> anyhow::Result<()> {
+    let initial_balance = U128::from(NearToken::from_near(10000).as_yoctonear());
+    let worker = near_workspaces::sandbox().await?;
+    let (ft_contract, owner, core_contract) = init(&worker, initial_balance).await?;
+    register_user(&ft_contract, core_contract.id()).await?;
+
+    let users = create_users(&worker, vec!["alice", "bob", "reviewer"], vec![10, 10, 10]).await?;
+    let alice = users.first().unwrap().clone();
+    let bob = users.get(1).unwrap().clone();
+    let reviewer = users.get(2).unwrap().clone();
+    for user in users.iter() {
+        deposit_storage(&core_contract, user.id(), NearToken::from_millinear(100)).await?;
+    }
+    register_user(&ft_contract, alice.id()).await?;
+
+    let res = owner
+        .call(ft_contract.id(), "ft_transfer")
+        .args_json((alice.id(), U128::from(NearToken::from_near(100).as_yoctonear()), "transfer to test account"))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let res = reviewer
+        .call(core_contract.id(), "create_reviewer")
+        .args_json(json!({"reviewer_id": reviewer.id()}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let deposit_amount = U128::from(NearToken::from_near(10).as_yoctonear());
+    let res = alice
+        .call(ft_contract.id(), "ft_transfer_call")
+        .args_json((core_contract.id(), deposit_amount, Option::<String>::None, json!({ "action": "deposit_to_reviewer", "reviewer_id": reviewer.id() }).to_string()))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    // Bob has no `mt_on_transfer`, the whole amount is refunded and alice's record is kept
+    let res = alice
+        .call(core_contract.id(), "mt_transfer_call")
+        .args_json(json!({"receiver_id": bob.id(), "token_id": reviewer.id(), "amount": deposit_amount, "msg": ""}))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    res.logs().iter().for_each(|log| println!("{:?}", log));
+    assert!(res.is_success());
+    assert!(res.logs().iter().any(|log| log.contains("\"mt_transfer\"") && log.contains("refund")));
+    assert_eq!(res.json::<Vec<U128>>()?, vec![U128(0)]);
+
+    let balances = core_contract
+        .call("mt_batch_balance_of")
+        .args_json(json!({"account_id": alice.id(), "token_ids": [reviewer.id()]}))
+        .view()
+        .await?
+        .json::<Vec<U128>>()?;
+    assert_eq!(balances, vec![deposit_amount]);
+
+    let bob_balance = core_contract
+        .call("mt_balance_of")
+        .args_json(json!({"account_id": bob.id(), "token_id": reviewer.id()}))
+        .view()
+        .await?
+        .json::<U128>()?;
+    assert_eq!(bob_balance, U128(0));
+
+    let supply = core_contract
+        .call("mt_supply")
+        .args_json(json!({"token_id": reviewer.id()}))
+        .view()
+        .await?
+        .json::<Option<U128>>()?;
+    assert_eq!(supply, Some(deposit_amount));
+
+    return Ok(());
+}
@@ -0,0 +1,110 @@
+#![allow(clippy::needless_return)]
+
+use near_sdk::{json_types::U128, NearToken};
+use serde_json::json;
+use ticle_core::GetDepositInfoResponse;
+
+use crate::common::utils::*;
+pub mod common;
+
+#[tokio::test]
+async fn test_ft_on_transfer_messages() -> anyhow::Result<()> {
+    let initial_balance = U128::from(NearToken::from_near(10000).as_yoctonear());
+    let worker = near_workspaces::sandbox().await?;
+    let (ft_contract, owner, core_contract) = init(&worker, initial_balance).await?;
+
+    register_user(&ft_contract, core_contract.id()).await?;
+
+    let users = create_users(&worker, vec!["alice", "reviewer"], vec![10, 10]).await?;
+    for user in users.iter() {
+        register_user(&ft_contract, user.id()).await?;
+        deposit_storage(&core_contract, user.id(), NearToken::from_millinear(100)).await?;
+
+        let res = owner
+            .call(ft_contract.id(), "ft_transfer")
+            .args_json((user.id(), U128::from(NearToken::from_near(100).as_yoctonear()), "transfer to test account"))
+            .max_gas()
+            .deposit(ONE_YOCTO)
+            .transact()
+            .await?;
+        assert!(res.is_success());
+    }
+
+    let alice = users.first().unwrap().clone();
+    let reviewer = users.get(1).unwrap().clone();
+
+    let res = reviewer
+        .call(core_contract.id(), "create_reviewer")
+        .args_json(json!({"reviewer_id": reviewer.id()}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let transfer_balance = U128::from(NearToken::from_near(10).as_yoctonear());
+    let invalid_messages = vec![
+        "not a json".to_string(),
+        json!({ "action": "unknown_action" }).to_string(),
+        json!({ "action": "deposit_to_reviewer", "version": 2, "reviewer_id": reviewer.id() }).to_string(),
+        // Unknown reviewer makes the action fail
+        json!({ "action": "deposit_to_reviewer", "reviewer_id": alice.id() }).to_string(),
+        // Legacy settlements without a settlement id are rejected
+        json!({ "vapi_ids": Vec::<String>::new(), "amounts": Vec::<U128>::new() }).to_string(),
+        // Only the owner can settle
+        json!({ "action": "settlement", "settlement_id": 1, "vapi_ids": Vec::<String>::new(), "amounts": Vec::<U128>::new() }).to_string(),
+    ];
+
+    // Invalid messages and failed actions are refunded to the sender
+    for msg in invalid_messages {
+        let res = alice
+            .call(ft_contract.id(), "ft_transfer_call")
+            .args_json((core_contract.id(), transfer_balance, Option::<String>::None, msg))
+            .max_gas()
+            .deposit(ONE_YOCTO)
+            .transact()
+            .await?;
+        res.logs().iter().for_each(|log| println!("{:?}", log));
+        assert!(res.is_success());
+        assert_eq!(res.json::<U128>()?, U128(0));
+
+        let alice_balance = ft_contract
+            .call("ft_balance_of")
+            .args_json(json!({"account_id": alice.id()}))
+            .view()
+            .await?
+            .json::<U128>()?;
+        assert_eq!(alice_balance, U128::from(NearToken::from_near(100).as_yoctonear()));
+    }
+
+    // Tagged message
+    let res = alice
+        .call(ft_contract.id(), "ft_transfer_call")
+        .args_json((core_contract.id(), transfer_balance, Option::<String>::None, json!({ "action": "deposit_to_reviewer", "version": 1, "reviewer_id": reviewer.id() }).to_string()))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+    assert_eq!(res.json::<U128>()?, transfer_balance);
+
+    // Legacy message
+    let res = alice
+        .call(ft_contract.id(), "ft_transfer_call")
+        .args_json((core_contract.id(), transfer_balance, Option::<String>::None, json!({ "reviewer_id": reviewer.id() }).to_string()))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+    assert_eq!(res.json::<U128>()?, transfer_balance);
+
+    let alice_deposit_info = core_contract
+        .call("get_delegator_deposit_info")
+        .args_json(json!({"delegator_id": alice.id(), "reviewer_id": reviewer.id()}))
+        .view()
+        .await?
+        .json::<GetDepositInfoResponse>()?;
+    assert_eq!(alice_deposit_info.deposit_amount, NearToken::from_near(20).as_yoctonear());
+
+    return Ok(());
+}
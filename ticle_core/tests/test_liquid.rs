@@ -0,0 +1,153 @@
+#![allow(clippy::needless_return)]
+
+use near_contract_standards::fungible_token::metadata::FungibleTokenMetadata;
+use near_sdk::{json_types::U128, NearToken};
+use serde_json::json;
+
+use crate::common::utils::*;
+pub mod common;
+
+#[tokio::test]
+async fn test_liquid_stake() -> anyhow::Result<()> {
+    let initial_balance = U128::from(NearToken::from_near(10000).as_yoctonear());
+    let worker = near_workspaces::sandbox().await?;
+    let (ft_contract, owner, core_contract) = init(&worker, initial_balance).await?;
+
+    register_user(&ft_contract, core_contract.id()).await?;
+    deposit_storage(&core_contract, owner.id(), NearToken::from_millinear(100)).await?;
+
+    let users = create_users(&worker, vec!["alice", "bob", "reviewer"], vec![10, 10, 10]).await?;
+    for user in users.iter() {
+        register_user(&ft_contract, user.id()).await?;
+        deposit_storage(&core_contract, user.id(), NearToken::from_millinear(100)).await?;
+
+        let res = owner
+            .call(ft_contract.id(), "ft_transfer")
+            .args_json((user.id(), U128::from(NearToken::from_near(100).as_yoctonear()), "transfer to test account"))
+            .max_gas()
+            .deposit(ONE_YOCTO)
+            .transact()
+            .await?;
+        assert!(res.is_success());
+    }
+
+    let alice = users.first().unwrap().clone();
+    let bob = users.get(1).unwrap().clone();
+    let reviewer = users.get(2).unwrap().clone();
+
+    let res = reviewer
+        .call(core_contract.id(), "create_reviewer")
+        .args_json(json!({"reviewer_id": reviewer.id()}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    // The liquid token of the reviewer holds the pooled delegation in core
+    let liquid_wasm = include_bytes!("../../target/wasm32-unknown-unknown/release/ticle_liquid.wasm");
+    let liquid_contract = worker.dev_deploy(liquid_wasm).await?;
+    let liquid_metadata = FungibleTokenMetadata {
+        spec: "ft-1.0.0".to_string(),
+        name: "Liquid Staked TICLE".to_string(),
+        symbol: "stTIC".to_string(),
+        icon: None,
+        reference: None,
+        reference_hash: None,
+        decimals: 24,
+    };
+    let res = liquid_contract
+        .call("new")
+        .args_json((core_contract.id(), reviewer.id(), liquid_metadata))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+    deposit_storage(&core_contract, liquid_contract.id(), NearToken::from_millinear(100)).await?;
+
+    let res = owner
+        .call(core_contract.id(), "create_liquid_pool")
+        .args_json(json!({"reviewer_id": reviewer.id(), "token_id": liquid_contract.id()}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let stake_amount = U128::from(NearToken::from_near(10).as_yoctonear());
+    for user in [&alice, &bob] {
+        let res = user
+            .call(ft_contract.id(), "ft_transfer_call")
+            .args_json((core_contract.id(), stake_amount, Option::<String>::None, json!({ "action": "stake_liquid", "reviewer_id": reviewer.id() }).to_string()))
+            .max_gas()
+            .deposit(ONE_YOCTO)
+            .transact()
+            .await?;
+        res.logs().iter().for_each(|log| println!("{:?}", log));
+        assert!(res.is_success());
+
+        let shares = liquid_contract
+            .call("ft_balance_of")
+            .args_json(json!({"account_id": user.id()}))
+            .view()
+            .await?
+            .json::<U128>()?;
+        assert_eq!(shares, stake_amount);
+    }
+
+    // Shares are transferable like any other fungible token
+    let res = alice
+        .call(liquid_contract.id(), "ft_transfer")
+        .args_json((bob.id(), U128::from(NearToken::from_near(5).as_yoctonear()), Option::<String>::None))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let res = bob
+        .call(liquid_contract.id(), "redeem")
+        .args_json(json!({"amount": U128::from(NearToken::from_near(15).as_yoctonear())}))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    res.logs().iter().for_each(|log| println!("{:?}", log));
+    assert!(res.is_success());
+
+    let pool = core_contract
+        .call("get_liquid_pool")
+        .args_json(json!({"reviewer_id": reviewer.id()}))
+        .view()
+        .await?
+        .json::<serde_json::Value>()?;
+    assert_eq!(pool["total_shares"], json!(U128::from(NearToken::from_near(5).as_yoctonear())));
+    assert_eq!(pool["total_amount"], json!(U128::from(NearToken::from_near(5).as_yoctonear())));
+
+    let bob_shares = liquid_contract
+        .call("ft_balance_of")
+        .args_json(json!({"account_id": bob.id()}))
+        .view()
+        .await?
+        .json::<U128>()?;
+    assert_eq!(bob_shares, U128(0));
+
+    // The redeemed value unbonds like a regular refund
+    worker.fast_forward(100).await?;
+    let res = bob
+        .call(core_contract.id(), "delegator_claim_refund")
+        .args_json(json!({"reviewer_id": reviewer.id()}))
+        .max_gas()
+        .transact()
+        .await?;
+    res.logs().iter().for_each(|log| println!("{:?}", log));
+    assert!(res.is_success());
+
+    let bob_balance = ft_contract
+        .call("ft_balance_of")
+        .args_json(json!({"account_id": bob.id()}))
+        .view()
+        .await?
+        .json::<U128>()?;
+    assert_eq!(bob_balance, U128::from(NearToken::from_near(105).as_yoctonear()));
+
+    return Ok(());
+}
@@ -0,0 +1,131 @@
+#![allow(clippy::needless_return)]
+
+use near_sdk::{json_types::U128, NearToken};
+use serde_json::json;
+
+use crate::common::utils::*;
+pub mod common;
+
+#[tokio::test]
+async fn test_ownership_transfer() -> anyhow::Result<()> {
+    let initial_balance = U128::from(NearToken::from_near(10000).as_yoctonear());
+    let worker = near_workspaces::sandbox().await?;
+    let (ft_contract, owner, core_contract) = init(&worker, initial_balance).await?;
+
+    register_user(&ft_contract, core_contract.id()).await?;
+    deposit_storage(&core_contract, owner.id(), NearToken::from_millinear(100)).await?;
+
+    let users = create_users(&worker, vec!["alice", "bob", "charlie"], vec![10, 10, 10]).await?;
+    let alice = users.first().unwrap().clone();
+    let bob = users.get(1).unwrap().clone();
+    let charlie = users.get(2).unwrap().clone();
+    deposit_storage(&core_contract, alice.id(), NearToken::from_millinear(100)).await?;
+    deposit_storage(&core_contract, bob.id(), NearToken::from_millinear(100)).await?;
+    register_user(&ft_contract, alice.id()).await?;
+
+    let vapi_id = "test-vapi";
+    let res = alice
+        .call(core_contract.id(), "create_vapi")
+        .args_json(json!({"vapi_id": vapi_id}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let amount = U128::from(NearToken::from_near(10).as_yoctonear());
+    let res = owner
+        .call(ft_contract.id(), "ft_transfer_call")
+        .args_json((core_contract.id(), amount, Option::<String>::None, json!({ "action": "settlement", "settlement_id": 1, "vapi_ids": [vapi_id], "amounts": [amount] }).to_string()))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    // Unregistered accounts cannot be proposed
+    let res = alice
+        .call(core_contract.id(), "propose_ownership_transfer")
+        .args_json(json!({"vapi_id": vapi_id, "new_coder_id": charlie.id(), "settle_rewards": true}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_failure());
+
+    let res = alice
+        .call(core_contract.id(), "propose_ownership_transfer")
+        .args_json(json!({"vapi_id": vapi_id, "new_coder_id": bob.id(), "settle_rewards": true}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    // Only the proposed coder can accept
+    let res = charlie
+        .call(core_contract.id(), "accept_ownership_transfer")
+        .args_json(json!({"vapi_id": vapi_id}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_failure());
+
+    let res = bob
+        .call(core_contract.id(), "accept_ownership_transfer")
+        .args_json(json!({"vapi_id": vapi_id}))
+        .max_gas()
+        .transact()
+        .await?;
+    res.logs().iter().for_each(|log| println!("{:?}", log));
+    assert!(res.is_success());
+
+    let pending_transfer = core_contract
+        .call("get_pending_ownership_transfer")
+        .args_json(json!({"vapi_id": vapi_id}))
+        .view()
+        .await?
+        .json::<Option<serde_json::Value>>()?;
+    assert!(pending_transfer.is_none());
+
+    // The rewards accrued by Alice were settled on acceptance
+    let alice_balance = ft_contract
+        .call("ft_balance_of")
+        .args_json(json!({"account_id": alice.id()}))
+        .view()
+        .await?
+        .json::<U128>()?;
+    assert_eq!(alice_balance, U128::from(NearToken::from_near(6).as_yoctonear()));
+
+    let vapi_reward_amount = core_contract
+        .call("get_vapi_reward_amount")
+        .args_json(json!({"vapi_id": vapi_id, "account_id": alice.id()}))
+        .view()
+        .await?
+        .json::<u128>()?;
+    assert_eq!(vapi_reward_amount, 0);
+
+    // A proposal can be declined by the proposed coder
+    let res = bob
+        .call(core_contract.id(), "propose_ownership_transfer")
+        .args_json(json!({"vapi_id": vapi_id, "new_coder_id": alice.id(), "settle_rewards": false}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let res = alice
+        .call(core_contract.id(), "cancel_ownership_transfer")
+        .args_json(json!({"vapi_id": vapi_id}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let res = alice
+        .call(core_contract.id(), "accept_ownership_transfer")
+        .args_json(json!({"vapi_id": vapi_id}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_failure());
+
+    return Ok(());
+}
@@ -0,0 +1,234 @@
+#![allow(clippy::needless_return)]
+
+use near_sdk::{json_types::U128, NearToken};
+use serde_json::json;
+
+use crate::common::utils::*;
+pub mod common;
+
+#[tokio::test]
+async fn test_reviewer_exit() -> anyhow::Result<()> {
+    let initial_balance = U128::from(NearToken::from_near(10000).as_yoctonear());
+    let worker = near_workspaces::sandbox().await?;
+    let (ft_contract, owner, core_contract) = init(&worker, initial_balance).await?;
+
+    register_user(&ft_contract, core_contract.id()).await?;
+    deposit_storage(&core_contract, owner.id(), NearToken::from_millinear(100)).await?;
+
+    let users = create_users(&worker, vec!["alice", "bob", "reviewer"], vec![10, 10, 10]).await?;
+    for user in users.iter() {
+        register_user(&ft_contract, user.id()).await?;
+        deposit_storage(&core_contract, user.id(), NearToken::from_millinear(100)).await?;
+
+        let res = owner
+            .call(ft_contract.id(), "ft_transfer")
+            .args_json((user.id(), U128::from(NearToken::from_near(100).as_yoctonear()), "transfer to test account"))
+            .max_gas()
+            .deposit(ONE_YOCTO)
+            .transact()
+            .await?;
+        assert!(res.is_success());
+    }
+
+    let alice = users.first().unwrap().clone();
+    let bob = users.get(1).unwrap().clone();
+    let reviewer = users.get(2).unwrap().clone();
+
+    let vapi_id = "test-vapi";
+    let res = owner
+        .call(core_contract.id(), "create_vapi")
+        .args_json(json!({"vapi_id": vapi_id}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    // A reviewer record is charged to the reviewer, so nobody else can create it
+    let res = owner
+        .call(core_contract.id(), "create_reviewer")
+        .args_json(json!({"reviewer_id": reviewer.id()}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_failure());
+
+    let res = reviewer
+        .call(core_contract.id(), "create_reviewer")
+        .args_json(json!({"reviewer_id": reviewer.id()}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let transfer_balance = U128::from(NearToken::from_near(10).as_yoctonear());
+    for user in [&alice, &bob] {
+        let res = user
+            .call(ft_contract.id(), "ft_transfer_call")
+            .args_json((core_contract.id(), transfer_balance, Option::<String>::None, json!({ "reviewer_id": reviewer.id() }).to_string()))
+            .max_gas()
+            .deposit(ONE_YOCTO)
+            .transact()
+            .await?;
+        assert!(res.is_success());
+    }
+
+    let res = reviewer
+        .call(core_contract.id(), "deposit_to_vapi")
+        .args_json(json!({"vapi_id": vapi_id, "amount": transfer_balance}))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let res = owner
+        .call(ft_contract.id(), "ft_transfer_call")
+        .args_json((core_contract.id(), transfer_balance, Option::<String>::None, json!({ "settlement_id": 1, "vapi_ids": [vapi_id], "amounts": [transfer_balance] }).to_string()))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    // Only the reviewer or the owner can start the exit
+    let res = alice
+        .call(core_contract.id(), "deregister_reviewer")
+        .args_json(json!({"reviewer_id": reviewer.id()}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_failure());
+
+    let res = reviewer
+        .call(core_contract.id(), "deregister_reviewer")
+        .args_json(json!({"reviewer_id": reviewer.id()}))
+        .max_gas()
+        .transact()
+        .await?;
+    res.logs().iter().for_each(|log| println!("{:?}", log));
+    assert!(res.is_success());
+
+    // Royalty = 10(Settlement) * 39%(Usage fee) * 1%(Royalty fee) = 0.039
+    let reviewer_balance = ft_contract
+        .call("ft_balance_of")
+        .args_json(json!({"account_id": reviewer.id()}))
+        .view()
+        .await?
+        .json::<U128>()?;
+    assert_eq!(reviewer_balance, U128(100_039_000_000_000_000_000_000_000));
+
+    // New delegations are refunded
+    let res = alice
+        .call(ft_contract.id(), "ft_transfer_call")
+        .args_json((core_contract.id(), transfer_balance, Option::<String>::None, json!({ "reviewer_id": reviewer.id() }).to_string()))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+    assert_eq!(res.json::<U128>()?, U128(0));
+
+    // The reviewer can't be removed while delegators are still bonded
+    let res = owner
+        .call(core_contract.id(), "remove_reviewer")
+        .args_json(json!({"reviewer_id": reviewer.id(), "limit": 10}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_failure());
+
+    let res = owner
+        .call(core_contract.id(), "unbond_reviewer_delegators")
+        .args_json(json!({"reviewer_id": reviewer.id(), "limit": 10}))
+        .max_gas()
+        .transact()
+        .await?;
+    res.logs().iter().for_each(|log| println!("{:?}", log));
+    assert!(res.is_success());
+    assert_eq!(res.json::<u64>()?, 0);
+
+    // Refunds still unbonding block the removal
+    let res = owner
+        .call(core_contract.id(), "remove_reviewer")
+        .args_json(json!({"reviewer_id": reviewer.id(), "limit": 10}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_failure());
+
+    // Deposit = 10, reward = 10 * 39% * 99% * 50% = 1.9305
+    let refunded_balance = U128(101_930_500_000_000_000_000_000_000);
+    worker.fast_forward(100).await?;
+    let res = alice
+        .call(core_contract.id(), "delegator_claim_refund")
+        .args_json(json!({"reviewer_id": reviewer.id()}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let balance = ft_contract
+        .call("ft_balance_of")
+        .args_json(json!({"account_id": alice.id()}))
+        .view()
+        .await?
+        .json::<U128>()?;
+    assert_eq!(balance, refunded_balance);
+
+    // Removal goes in pages, an empty page leaves Bob's record in place
+    let res = owner
+        .call(core_contract.id(), "remove_reviewer")
+        .args_json(json!({"reviewer_id": reviewer.id(), "limit": 0}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+    assert_eq!(res.json::<u64>()?, 1);
+
+    // Bob's unclaimed refund doesn't block the removal, it is held for him instead
+    let res = owner
+        .call(core_contract.id(), "remove_reviewer")
+        .args_json(json!({"reviewer_id": reviewer.id(), "limit": 10}))
+        .max_gas()
+        .transact()
+        .await?;
+    res.logs().iter().for_each(|log| println!("{:?}", log));
+    assert!(res.is_success());
+    assert_eq!(res.json::<u64>()?, 0);
+
+    let res = bob
+        .call(core_contract.id(), "claim_exit_refund")
+        .args_json(json!({}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let balance = ft_contract
+        .call("ft_balance_of")
+        .args_json(json!({"account_id": bob.id()}))
+        .view()
+        .await?
+        .json::<U128>()?;
+    assert_eq!(balance, refunded_balance);
+
+    let exit_refund = core_contract
+        .call("get_exit_refund")
+        .args_json(json!({"account_id": bob.id()}))
+        .view()
+        .await?
+        .json::<U128>()?;
+    assert_eq!(exit_refund, U128(0));
+
+    // Every record of the reviewer is gone, so the reviewer can leave the contract
+    let res = reviewer
+        .call(core_contract.id(), "storage_unregister")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    return Ok(());
+}
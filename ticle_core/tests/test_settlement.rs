@@ -0,0 +1,146 @@
+#![allow(clippy::needless_return)]
+
+use near_sdk::{json_types::U128, NearToken};
+use near_workspaces::{Account, Contract};
+use serde_json::json;
+use ticle_core::SettlementReceipt;
+
+use crate::common::utils::*;
+pub mod common;
+
+async fn settle(owner: &Account, ft_contract: &Contract, core_contract: &Contract, amount: U128, msg: serde_json::Value) -> anyhow::Result<U128> {
+    let res = owner
+        .call(ft_contract.id(), "ft_transfer_call")
+        .args_json((core_contract.id(), amount, Option::<String>::None, msg.to_string()))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    res.logs().iter().for_each(|log| println!("{:?}", log));
+    assert!(res.is_success());
+
+    return Ok(res.json::<U128>()?);
+}
+
+#[tokio::test]
+async fn test_settlement_receipt() -> anyhow::Result<()> {
+    let initial_balance = U128::from(NearToken::from_near(10000).as_yoctonear());
+    let worker = near_workspaces::sandbox().await?;
+    let (ft_contract, owner, core_contract) = init(&worker, initial_balance).await?;
+
+    register_user(&ft_contract, core_contract.id()).await?;
+    deposit_storage(&core_contract, owner.id(), NearToken::from_millinear(100)).await?;
+
+    let vapi_ids = vec!["test-vapi-a", "test-vapi-b"];
+    for vapi_id in vapi_ids.iter() {
+        let res = owner
+            .call(core_contract.id(), "create_vapi")
+            .args_json(json!({"vapi_id": vapi_id}))
+            .max_gas()
+            .transact()
+            .await?;
+        assert!(res.is_success());
+    }
+
+    let amounts = vec![U128::from(NearToken::from_near(10).as_yoctonear()), U128::from(NearToken::from_near(20).as_yoctonear())];
+    let total_amount = U128::from(NearToken::from_near(30).as_yoctonear());
+    let msg = json!({ "action": "settlement", "settlement_id": 7, "vapi_ids": vapi_ids, "amounts": amounts });
+
+    let used_amount = settle(&owner, &ft_contract, &core_contract, total_amount, msg.clone()).await?;
+    assert_eq!(used_amount, total_amount);
+
+    // No reviewer deposited, so the whole reviewer fee goes to the treasury
+    let receipt = core_contract
+        .call("get_settlement_receipt")
+        .args_json(json!({"settlement_id": 7}))
+        .view()
+        .await?
+        .json::<Option<SettlementReceipt>>()?
+        .unwrap();
+    assert_eq!(receipt.vapi_ids, vapi_ids);
+    assert_eq!(receipt.amounts, vec![NearToken::from_near(10).as_yoctonear(), NearToken::from_near(20).as_yoctonear()]);
+    assert_eq!(receipt.reviewer_fee_amount, NearToken::from_millinear(11700).as_yoctonear());
+    assert_eq!(receipt.treasury_amount, NearToken::from_millinear(11700).as_yoctonear());
+    assert_eq!(receipt.burn_amount, NearToken::from_millinear(300).as_yoctonear());
+
+    // The burn debits the core contract, not the owner who signed the settlement
+    let total_burned_amount = core_contract
+        .call("get_total_burned_amount")
+        .view()
+        .await?
+        .json::<u128>()?;
+    assert_eq!(total_burned_amount, NearToken::from_millinear(300).as_yoctonear());
+
+    let core_balance = ft_contract
+        .call("ft_balance_of")
+        .args_json(json!({"account_id": core_contract.id()}))
+        .view()
+        .await?
+        .json::<U128>()?;
+    assert_eq!(core_balance, U128::from(NearToken::from_millinear(29700).as_yoctonear()));
+
+    let owner_balance = ft_contract
+        .call("ft_balance_of")
+        .args_json(json!({"account_id": owner.id()}))
+        .view()
+        .await?
+        .json::<U128>()?;
+    assert_eq!(owner_balance.0, initial_balance.0 - total_amount.0);
+
+    // A replayed settlement is refunded
+    let used_amount = settle(&owner, &ft_contract, &core_contract, total_amount, msg).await?;
+    assert_eq!(used_amount, U128(0));
+
+    let receipt = core_contract
+        .call("get_settlement_receipt")
+        .args_json(json!({"settlement_id": 8}))
+        .view()
+        .await?
+        .json::<Option<SettlementReceipt>>()?;
+    assert!(receipt.is_none());
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn test_settlement_amount_mismatch() -> anyhow::Result<()> {
+    let initial_balance = U128::from(NearToken::from_near(10000).as_yoctonear());
+    let worker = near_workspaces::sandbox().await?;
+    let (ft_contract, owner, core_contract) = init(&worker, initial_balance).await?;
+
+    register_user(&ft_contract, core_contract.id()).await?;
+    deposit_storage(&core_contract, owner.id(), NearToken::from_millinear(100)).await?;
+
+    let vapi_id = "test-vapi";
+    let res = owner
+        .call(core_contract.id(), "create_vapi")
+        .args_json(json!({"vapi_id": vapi_id}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let settlement_amount = U128::from(NearToken::from_near(10).as_yoctonear());
+
+    // Amounts larger than the transfer are refunded
+    let msg = json!({ "action": "settlement", "settlement_id": 1, "vapi_ids": vec![vapi_id], "amounts": vec![settlement_amount] });
+    let used_amount = settle(&owner, &ft_contract, &core_contract, U128::from(NearToken::from_near(9).as_yoctonear()), msg).await?;
+    assert_eq!(used_amount, U128(0));
+
+    // The surplus of the transfer goes to the treasury
+    let msg = json!({ "action": "settlement", "settlement_id": 1, "vapi_ids": vec![vapi_id], "amounts": vec![settlement_amount] });
+    let used_amount = settle(&owner, &ft_contract, &core_contract, U128::from(NearToken::from_near(12).as_yoctonear()), msg).await?;
+    assert_eq!(used_amount, U128::from(NearToken::from_near(12).as_yoctonear()));
+
+    let receipt = core_contract
+        .call("get_settlement_receipt")
+        .args_json(json!({"settlement_id": 1}))
+        .view()
+        .await?
+        .json::<Option<SettlementReceipt>>()?
+        .unwrap();
+    assert_eq!(receipt.surplus_amount, NearToken::from_near(2).as_yoctonear());
+    assert_eq!(receipt.treasury_amount, NearToken::from_millinear(5900).as_yoctonear());
+
+    return Ok(());
+}
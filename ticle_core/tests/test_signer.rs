@@ -0,0 +1,97 @@
+#![allow(clippy::needless_return)]
+
+use near_crypto::{KeyType, SecretKey};
+use near_sdk::{json_types::U128, NearToken};
+use serde_json::json;
+use ticle_core::signer::SignerView;
+
+use crate::common::utils::*;
+pub mod common;
+
+#[tokio::test]
+async fn test_signer_rotation_and_threshold() -> anyhow::Result<()> {
+    let initial_balance = U128::from(NearToken::from_near(10000).as_yoctonear());
+    let worker = near_workspaces::sandbox().await?;
+    let (_ft_contract, owner, core_contract) = init(&worker, initial_balance).await?;
+
+    let owner_secret_key: SecretKey = owner.secret_key().to_string().parse()?;
+    let new_secret_key = SecretKey::from_random(KeyType::ED25519);
+    let second_secret_key = SecretKey::from_random(KeyType::ED25519);
+
+    let message = "usage,test-vapi,100";
+    let sign = |secret_key: &SecretKey| json!({
+        "public_key": secret_key.public_key().to_string(),
+        "signature": secret_key.sign(message.as_bytes()).to_string(),
+    });
+    let verify = |signatures: serde_json::Value, amount: u128| {
+        let core_contract = core_contract.clone();
+        async move {
+            core_contract
+                .call("verify_signatures")
+                .args_json(json!({"message": message, "signatures": signatures, "amount": U128(amount)}))
+                .view()
+                .await?
+                .json::<bool>()
+        }
+    };
+
+    assert!(verify(json!([sign(&owner_secret_key)]), 100).await?);
+    assert!(!verify(json!([sign(&new_secret_key)]), 100).await?);
+
+    // Both keys are valid during the grace period of the rotation
+    let res = owner
+        .call(core_contract.id(), "rotate_signer")
+        .args_json(json!({
+            "old_public_key": owner_secret_key.public_key().to_string(),
+            "new_public_key": new_secret_key.public_key().to_string(),
+            "grace_period": 60 * 60 * 1000,
+        }))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    assert!(verify(json!([sign(&owner_secret_key)]), 100).await?);
+    assert!(verify(json!([sign(&new_secret_key)]), 100).await?);
+
+    let signers = core_contract.call("get_active_signers").view().await?.json::<Vec<SignerView>>()?;
+    assert_eq!(signers.len(), 2);
+
+    // A leaked key is revoked right away
+    let res = owner
+        .call(core_contract.id(), "remove_signer")
+        .args_json(json!({"public_key": owner_secret_key.public_key().to_string()}))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+    assert!(!verify(json!([sign(&owner_secret_key)]), 100).await?);
+
+    // High value payloads require 2 of the 2 signers
+    let res = owner
+        .call(core_contract.id(), "add_signer")
+        .args_json(json!({"public_key": second_secret_key.public_key().to_string()}))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let res = owner
+        .call(core_contract.id(), "set_signer_threshold")
+        .args_json(json!({"threshold": 2, "high_value_amount": U128(1000)}))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    assert!(verify(json!([sign(&new_secret_key)]), 999).await?);
+    assert!(!verify(json!([sign(&new_secret_key)]), 1000).await?);
+    assert!(!verify(json!([sign(&new_secret_key), sign(&new_secret_key)]), 1000).await?);
+    assert!(verify(json!([sign(&new_secret_key), sign(&second_secret_key)]), 1000).await?);
+
+    return Ok(());
+}
@@ -0,0 +1,133 @@
+#![allow(clippy::needless_return)]
+
+use near_contract_standards::storage_management::StorageBalance;
+use near_sdk::{json_types::U128, NearToken};
+use serde_json::json;
+
+use crate::common::utils::*;
+pub mod common;
+
+#[tokio::test]
+async fn test_storage_management() -> anyhow::Result<()> {
+    let initial_balance = U128::from(NearToken::from_near(10000).as_yoctonear());
+    let worker = near_workspaces::sandbox().await?;
+    let (ft_contract, _owner, core_contract) = init(&worker, initial_balance).await?;
+
+    register_user(&ft_contract, core_contract.id()).await?;
+
+    let users = create_users(&worker, vec!["alice", "bob"], vec![10, 10]).await?;
+    let alice = users.first().unwrap().clone();
+    let bob = users.get(1).unwrap().clone();
+
+    // Unregistered accounts cannot create records
+    let vapi_id = "test-vapi";
+    let res = alice
+        .call(core_contract.id(), "create_vapi")
+        .args_json(json!({"vapi_id": vapi_id}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_failure());
+
+    let res = bob
+        .call(core_contract.id(), "create_reviewer")
+        .args_json(json!({"reviewer_id": bob.id()}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_failure());
+
+    deposit_storage(&core_contract, alice.id(), NearToken::from_millinear(100)).await?;
+
+    let res = alice
+        .call(core_contract.id(), "create_vapi")
+        .args_json(json!({"vapi_id": vapi_id}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    // The VAPI record is charged against Alice's storage deposit
+    let storage_balance = core_contract
+        .call("storage_balance_of")
+        .args_json(json!({"account_id": alice.id()}))
+        .view()
+        .await?
+        .json::<Option<StorageBalance>>()?
+        .unwrap();
+    assert_eq!(storage_balance.total, NearToken::from_millinear(100));
+    assert!(storage_balance.available < storage_balance.total);
+
+    // Records still in use cannot be withdrawn or unregistered
+    let res = alice
+        .call(core_contract.id(), "storage_withdraw")
+        .args_json(json!({"amount": storage_balance.total}))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_failure());
+
+    let res = alice
+        .call(core_contract.id(), "storage_unregister")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_failure());
+
+    // The ownership transfer proposal is charged to Alice as well
+    deposit_storage(&core_contract, bob.id(), NearToken::from_millinear(100)).await?;
+    let res = alice
+        .call(core_contract.id(), "propose_ownership_transfer")
+        .args_json(json!({"vapi_id": vapi_id, "new_coder_id": bob.id(), "settle_rewards": false}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let storage_balance = core_contract
+        .call("storage_balance_of")
+        .args_json(json!({"account_id": alice.id()}))
+        .view()
+        .await?
+        .json::<Option<StorageBalance>>()?
+        .unwrap();
+    let res = alice
+        .call(core_contract.id(), "storage_withdraw")
+        .args_json(json!({"amount": storage_balance.available}))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    // Accepting the transfer moves the record's storage to the new coder
+    let res = bob
+        .call(core_contract.id(), "accept_ownership_transfer")
+        .args_json(json!({"vapi_id": vapi_id}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let res = alice
+        .call(core_contract.id(), "storage_unregister")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let storage_balance = core_contract
+        .call("storage_balance_of")
+        .args_json(json!({"account_id": alice.id()}))
+        .view()
+        .await?
+        .json::<Option<StorageBalance>>()?;
+    assert!(storage_balance.is_none());
+
+    return Ok(());
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::needless_return)]
+
 use std::str::FromStr;
 
 use near_sdk::{json_types::U128, NearToken};
@@ -15,10 +17,12 @@ async fn test_storage_simulation() -> anyhow::Result<()> {
     let (ft_contract, owner, core_contract) = init(&worker, initial_balance).await?;
     
     register_user(&ft_contract, core_contract.id()).await?;
+    deposit_storage(&core_contract, owner.id(), NearToken::from_millinear(100)).await?;
 
     let users = create_users(&worker, vec!["alice", "bob", "charlie"], vec![10, 10, 10]).await?;
     for user in users.iter() {
         register_user(&ft_contract, user.id()).await?;
+        deposit_storage(&core_contract, user.id(), NearToken::from_millinear(100)).await?;
 
         let res = owner.transfer_near(user.id(), NearToken::from_near(1)).await?;
         assert!(res.is_success());
@@ -33,7 +37,7 @@ async fn test_storage_simulation() -> anyhow::Result<()> {
         assert!(res.is_success());
     }
 
-    let alice = users.get(0).unwrap().clone();
+    let alice = users.first().unwrap().clone();
     let bob = users.get(1).unwrap().clone();
     let charlie = users.get(2).unwrap().clone();
 
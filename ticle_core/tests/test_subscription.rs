@@ -0,0 +1,232 @@
+#![allow(clippy::needless_return)]
+
+use near_sdk::{json_types::U128, NearToken};
+use serde_json::json;
+use ticle_core::subscription::Subscription;
+
+use crate::common::utils::*;
+pub mod common;
+
+#[tokio::test]
+async fn test_subscription() -> anyhow::Result<()> {
+    let initial_balance = U128::from(NearToken::from_near(10000).as_yoctonear());
+    let worker = near_workspaces::sandbox().await?;
+    let (ft_contract, owner, core_contract) = init(&worker, initial_balance).await?;
+
+    register_user(&ft_contract, core_contract.id()).await?;
+
+    let users = create_users(&worker, vec!["coder", "consumer"], vec![10, 10]).await?;
+    for user in users.iter() {
+        register_user(&ft_contract, user.id()).await?;
+        deposit_storage(&core_contract, user.id(), NearToken::from_millinear(100)).await?;
+
+        let res = owner
+            .call(ft_contract.id(), "ft_transfer")
+            .args_json((user.id(), U128::from(NearToken::from_near(100).as_yoctonear()), "transfer to test account"))
+            .max_gas()
+            .deposit(ONE_YOCTO)
+            .transact()
+            .await?;
+        assert!(res.is_success());
+    }
+
+    let coder = users.first().unwrap().clone();
+    let consumer = users.get(1).unwrap().clone();
+
+    let vapi_id = "test-vapi";
+    let res = coder
+        .call(core_contract.id(), "create_vapi")
+        .args_json(json!({"vapi_id": vapi_id}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let price = NearToken::from_near(10).as_yoctonear();
+    let res = coder
+        .call(core_contract.id(), "create_subscription_plan")
+        .args_json(json!({"vapi_id": vapi_id, "price": U128(price), "period_duration": 30 * 24 * 60 * 60 * 1000u64, "quota": 1000}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+    let plan_id = res.json::<u64>()?;
+
+    // Amounts which aren't a multiple of the price are refunded
+    let msg = json!({ "action": "subscribe", "plan_id": plan_id }).to_string();
+    let res = consumer
+        .call(ft_contract.id(), "ft_transfer_call")
+        .args_json((core_contract.id(), U128(price / 2), Option::<String>::None, msg.clone()))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+    assert_eq!(res.json::<U128>()?, U128(0));
+
+    let res = consumer
+        .call(ft_contract.id(), "ft_transfer_call")
+        .args_json((core_contract.id(), U128(price * 2), Option::<String>::None, msg))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+    assert_eq!(res.json::<U128>()?, U128(price * 2));
+
+    let is_active = core_contract
+        .call("is_subscription_active")
+        .args_json(json!({"consumer_id": consumer.id(), "plan_id": plan_id}))
+        .view()
+        .await?
+        .json::<bool>()?;
+    assert!(is_active);
+
+    let subscription = core_contract
+        .call("get_subscription")
+        .args_json(json!({"consumer_id": consumer.id(), "plan_id": plan_id}))
+        .view()
+        .await?
+        .json::<Option<Subscription>>()?
+        .unwrap();
+    assert_eq!(subscription.escrow_amount, price * 2);
+    assert_eq!(subscription.paid_until - subscription.settled_until, 2 * 30 * 24 * 60 * 60 * 1000);
+
+    // No period has elapsed yet
+    let res = consumer
+        .call(core_contract.id(), "settle_subscription")
+        .args_json(json!({"consumer_id": consumer.id(), "plan_id": plan_id}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_failure());
+
+    // Cancelling right away refunds all but the prorated seconds of the first period
+    let res = consumer
+        .call(core_contract.id(), "cancel_subscription")
+        .args_json(json!({"plan_id": plan_id}))
+        .max_gas()
+        .transact()
+        .await?;
+    res.logs().iter().for_each(|log| println!("{:?}", log));
+    assert!(res.is_success());
+
+    let consumer_balance = ft_contract
+        .call("ft_balance_of")
+        .args_json(json!({"account_id": consumer.id()}))
+        .view()
+        .await?
+        .json::<U128>()?;
+    assert!(consumer_balance.0 > NearToken::from_near(99).as_yoctonear());
+    assert!(consumer_balance.0 < NearToken::from_near(100).as_yoctonear());
+
+    let subscription = core_contract
+        .call("get_subscription")
+        .args_json(json!({"consumer_id": consumer.id(), "plan_id": plan_id}))
+        .view()
+        .await?
+        .json::<Option<Subscription>>()?;
+    assert!(subscription.is_none());
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn test_cancel_subscription_failed_refund() -> anyhow::Result<()> {
+    let initial_balance = U128::from(NearToken::from_near(10000).as_yoctonear());
+    let worker = near_workspaces::sandbox().await?;
+    let (ft_contract, owner, core_contract) = init(&worker, initial_balance).await?;
+
+    register_user(&ft_contract, core_contract.id()).await?;
+
+    let users = create_users(&worker, vec!["coder", "consumer"], vec![10, 10]).await?;
+    for user in users.iter() {
+        register_user(&ft_contract, user.id()).await?;
+        deposit_storage(&core_contract, user.id(), NearToken::from_millinear(100)).await?;
+    }
+    let coder = users.first().unwrap().clone();
+    let consumer = users.get(1).unwrap().clone();
+
+    let price = NearToken::from_near(10).as_yoctonear();
+    let res = owner
+        .call(ft_contract.id(), "ft_transfer")
+        .args_json((consumer.id(), U128(price), "transfer to test account"))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let vapi_id = "test-vapi";
+    let res = coder
+        .call(core_contract.id(), "create_vapi")
+        .args_json(json!({"vapi_id": vapi_id}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let res = coder
+        .call(core_contract.id(), "create_subscription_plan")
+        .args_json(json!({"vapi_id": vapi_id, "price": U128(price), "period_duration": 30 * 24 * 60 * 60 * 1000u64, "quota": 1000}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+    let plan_id = res.json::<u64>()?;
+
+    let res = consumer
+        .call(ft_contract.id(), "ft_transfer_call")
+        .args_json((core_contract.id(), U128(price), Option::<String>::None, json!({ "action": "subscribe", "plan_id": plan_id }).to_string()))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    // The consumer leaves the token and spends its spare storage deposit, so the refund can't be transferred
+    let res = consumer
+        .call(ft_contract.id(), "storage_unregister")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let res = consumer
+        .call(core_contract.id(), "storage_withdraw")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let res = consumer
+        .call(core_contract.id(), "cancel_subscription")
+        .args_json(json!({"plan_id": plan_id}))
+        .max_gas()
+        .transact()
+        .await?;
+    res.logs().iter().for_each(|log| println!("{:?}", log));
+    assert!(res.is_success());
+
+    // The refund is kept for the consumer, either as credit or as a claimable refund
+    let consumer_balance = core_contract
+        .call("get_consumer_balance")
+        .args_json(json!({"consumer_id": consumer.id()}))
+        .view()
+        .await?
+        .json::<u128>()?;
+    let exit_refund = core_contract
+        .call("get_exit_refund")
+        .args_json(json!({"account_id": consumer.id()}))
+        .view()
+        .await?
+        .json::<U128>()?;
+    assert!(consumer_balance + exit_refund.0 > NearToken::from_near(9).as_yoctonear());
+    assert!(consumer_balance + exit_refund.0 < price);
+
+    return Ok(());
+}
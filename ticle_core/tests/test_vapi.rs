@@ -1,3 +1,5 @@
+#![allow(clippy::needless_return)]
+
 use near_sdk::{json_types::U128, NearToken};
 use serde_json::json;
 use ticle_core::GetDepositInfoResponse;
@@ -12,10 +14,12 @@ async fn test_deposit() -> anyhow::Result<()> {
     let (ft_contract, owner, core_contract) = init(&worker, initial_balance).await?;
 
     register_user(&ft_contract, core_contract.id()).await?;
+    deposit_storage(&core_contract, owner.id(), NearToken::from_millinear(100)).await?;
 
     let users = create_users(&worker, vec!["alice", "bob", "reviewer"], vec![10, 10, 10]).await?;
     for user in users.iter() {
         register_user(&ft_contract, user.id()).await?;
+        deposit_storage(&core_contract, user.id(), NearToken::from_millinear(100)).await?;
 
         let res = owner.transfer_near(user.id(), NearToken::from_near(1)).await?;
         assert!(res.is_success());
@@ -30,7 +34,7 @@ async fn test_deposit() -> anyhow::Result<()> {
         assert!(res.is_success());
     }
 
-    let alice = users.get(0).unwrap().clone();
+    let alice = users.first().unwrap().clone();
     let bob = users.get(1).unwrap().clone();
     let reviewer = users.get(2).unwrap().clone();
 
@@ -43,7 +47,7 @@ async fn test_deposit() -> anyhow::Result<()> {
         .await?;
     assert!(res.is_success());
 
-    let res = owner
+    let res = reviewer
         .call(core_contract.id(), "create_reviewer")
         .args_json(json!({"reviewer_id": reviewer.id()}))
         .max_gas()
@@ -92,7 +96,7 @@ async fn test_deposit() -> anyhow::Result<()> {
     // Settle 10 tokens
     let res = owner
         .call(ft_contract.id(), "ft_transfer_call")
-        .args_json((core_contract.id(), transfer_balance, Option::<String>::None, serde_json::json!({ "vapi_ids": vec![vapi_id], "amounts": vec![transfer_balance] }).to_string()))
+        .args_json((core_contract.id(), transfer_balance, Option::<String>::None, serde_json::json!({ "settlement_id": 1, "vapi_ids": vec![vapi_id], "amounts": vec![transfer_balance] }).to_string()))
         .max_gas()
         .deposit(ONE_YOCTO)
         .transact()
@@ -167,7 +171,7 @@ async fn test_deposit() -> anyhow::Result<()> {
     // Settle 10 tokens
     let res = owner
         .call(ft_contract.id(), "ft_transfer_call")
-        .args_json((core_contract.id(), transfer_balance, Option::<String>::None, serde_json::json!({ "vapi_ids": vec![vapi_id], "amounts": vec![transfer_balance] }).to_string()))
+        .args_json((core_contract.id(), transfer_balance, Option::<String>::None, serde_json::json!({ "settlement_id": 2, "vapi_ids": vec![vapi_id], "amounts": vec![transfer_balance] }).to_string()))
         .max_gas()
         .deposit(ONE_YOCTO)
         .transact()
@@ -218,10 +222,12 @@ let initial_balance = U128::from(NearToken::from_near(10000).as_yoctonear());
     let (ft_contract, owner, core_contract) = init(&worker, initial_balance).await?;
 
     register_user(&ft_contract, core_contract.id()).await?;
+    deposit_storage(&core_contract, owner.id(), NearToken::from_millinear(100)).await?;
 
     let users = create_users(&worker, vec!["alice", "reviewer"], vec![10, 10]).await?;
     for user in users.iter() {
         register_user(&ft_contract, user.id()).await?;
+        deposit_storage(&core_contract, user.id(), NearToken::from_millinear(100)).await?;
 
         let res = owner.transfer_near(user.id(), NearToken::from_near(1)).await?;
         assert!(res.is_success());
@@ -236,7 +242,7 @@ let initial_balance = U128::from(NearToken::from_near(10000).as_yoctonear());
         assert!(res.is_success());
     }
 
-    let alice = users.get(0).unwrap().clone();
+    let alice = users.first().unwrap().clone();
     let reviewer = users.get(1).unwrap().clone();
 
     let vapi_id = "test-vapi";
@@ -248,7 +254,7 @@ let initial_balance = U128::from(NearToken::from_near(10000).as_yoctonear());
         .await?;
     assert!(res.is_success());
 
-    let res = owner
+    let res = reviewer
         .call(core_contract.id(), "create_reviewer")
         .args_json(json!({"reviewer_id": reviewer.id()}))
         .max_gas()
@@ -321,7 +327,7 @@ let initial_balance = U128::from(NearToken::from_near(10000).as_yoctonear());
     let settlement_amount = U128::from(NearToken::from_near(10).as_yoctonear());
     let res = owner
         .call(ft_contract.id(), "ft_transfer_call")
-        .args_json((core_contract.id(), settlement_amount, Option::<String>::None, serde_json::json!({ "vapi_ids": vec![vapi_id], "amounts": vec![settlement_amount] }).to_string()))
+        .args_json((core_contract.id(), settlement_amount, Option::<String>::None, serde_json::json!({ "settlement_id": 1, "vapi_ids": vec![vapi_id], "amounts": vec![settlement_amount] }).to_string()))
         .max_gas()
         .deposit(ONE_YOCTO)
         .transact()
@@ -359,10 +365,12 @@ async fn test_refund() -> anyhow::Result<()> {
     let (ft_contract, owner, core_contract) = init(&worker, initial_balance).await?;
 
     register_user(&ft_contract, core_contract.id()).await?;
+    deposit_storage(&core_contract, owner.id(), NearToken::from_millinear(100)).await?;
 
     let users = create_users(&worker, vec!["alice", "reviewer"], vec![10, 10]).await?;
     for user in users.iter() {
         register_user(&ft_contract, user.id()).await?;
+        deposit_storage(&core_contract, user.id(), NearToken::from_millinear(100)).await?;
 
         let res = owner.transfer_near(user.id(), NearToken::from_near(1)).await?;
         assert!(res.is_success());
@@ -380,10 +388,10 @@ async fn test_refund() -> anyhow::Result<()> {
     let res = owner.transfer_near(core_contract.id(), NearToken::from_near(1)).await?;
     assert!(res.is_success());
 
-    let alice = users.get(0).unwrap().clone();
+    let alice = users.first().unwrap().clone();
     let reviewer = users.get(1).unwrap().clone();
 
-    let vapi_ids = vec!["test-vapi-a", "test-vapi-b"];
+    let vapi_ids = ["test-vapi-a", "test-vapi-b"];
     for vapi_id in vapi_ids.iter() {
         let res = owner
             .call(core_contract.id(), "create_vapi")
@@ -394,7 +402,7 @@ async fn test_refund() -> anyhow::Result<()> {
         assert!(res.is_success());
     }
 
-    let res = owner
+    let res = reviewer
         .call(core_contract.id(), "create_reviewer")
         .args_json(json!({"reviewer_id": reviewer.id()}))
         .max_gas()
@@ -0,0 +1,223 @@
+#![allow(clippy::needless_return)]
+
+use near_contract_standards::non_fungible_token::Token;
+use near_contract_standards::storage_management::StorageBalance;
+use near_sdk::{json_types::U128, NearToken};
+use serde_json::json;
+
+use crate::common::utils::*;
+pub mod common;
+
+#[tokio::test]
+async fn test_vapi_nft_transfer() -> anyhow::Result<()> {
+    let initial_balance = U128::from(NearToken::from_near(10000).as_yoctonear());
+    let worker = near_workspaces::sandbox().await?;
+    let (ft_contract, owner, core_contract) = init(&worker, initial_balance).await?;
+
+    register_user(&ft_contract, core_contract.id()).await?;
+    deposit_storage(&core_contract, owner.id(), NearToken::from_millinear(100)).await?;
+
+    let users = create_users(&worker, vec!["alice", "bob", "charlie"], vec![10, 10, 10]).await?;
+    let alice = users.first().unwrap().clone();
+    let bob = users.get(1).unwrap().clone();
+    let charlie = users.get(2).unwrap().clone();
+    deposit_storage(&core_contract, alice.id(), NearToken::from_millinear(100)).await?;
+    deposit_storage(&core_contract, bob.id(), NearToken::from_millinear(100)).await?;
+    register_user(&ft_contract, alice.id()).await?;
+
+    let alice_storage_balance = core_contract
+        .call("storage_balance_of")
+        .args_json(json!({"account_id": alice.id()}))
+        .view()
+        .await?
+        .json::<StorageBalance>()?;
+
+    let vapi_id = "test-vapi";
+    let res = alice
+        .call(core_contract.id(), "create_vapi")
+        .args_json(json!({"vapi_id": vapi_id}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let token = core_contract
+        .call("nft_token")
+        .args_json(json!({"token_id": vapi_id}))
+        .view()
+        .await?
+        .json::<Option<Token>>()?
+        .unwrap();
+    assert_eq!(token.owner_id.as_str(), alice.id().as_str());
+
+    let amount = U128::from(NearToken::from_near(10).as_yoctonear());
+    let res = owner
+        .call(ft_contract.id(), "ft_transfer_call")
+        .args_json((core_contract.id(), amount, Option::<String>::None, json!({ "action": "settlement", "settlement_id": 1, "vapi_ids": [vapi_id], "amounts": [amount] }).to_string()))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    // The receiver has to be registered with the core contract to hold the VAPI
+    let res = alice
+        .call(core_contract.id(), "nft_transfer")
+        .args_json(json!({"receiver_id": charlie.id(), "token_id": vapi_id}))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_failure());
+
+    // An approved account can transfer the VAPI on behalf of the coder
+    let res = alice
+        .call(core_contract.id(), "nft_approve")
+        .args_json(json!({"token_id": vapi_id, "account_id": charlie.id()}))
+        .max_gas()
+        .deposit(NearToken::from_millinear(10))
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let res = charlie
+        .call(core_contract.id(), "nft_transfer")
+        .args_json(json!({"receiver_id": bob.id(), "token_id": vapi_id}))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    res.logs().iter().for_each(|log| println!("{:?}", log));
+    assert!(res.is_success());
+
+    let tokens = core_contract
+        .call("nft_tokens_for_owner")
+        .args_json(json!({"account_id": bob.id()}))
+        .view()
+        .await?
+        .json::<Vec<Token>>()?;
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(tokens[0].token_id, vapi_id);
+
+    // The storage of the VAPI moves with it, the approval was paid by alice's attached deposit
+    let storage_balance = core_contract
+        .call("storage_balance_of")
+        .args_json(json!({"account_id": alice.id()}))
+        .view()
+        .await?
+        .json::<StorageBalance>()?;
+    assert_eq!(storage_balance.available, alice_storage_balance.available);
+
+    // Bob is the coder now, while the rewards accrued before the transfer stay with alice
+    let res = bob
+        .call(core_contract.id(), "set_contributors")
+        .args_json(json!({"vapi_id": vapi_id, "account_ids": [charlie.id()], "share_bps": [1000]}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    for (account_id, reward_amount) in [(alice.id(), NearToken::from_near(6)), (bob.id(), NearToken::from_near(0))] {
+        let vapi_reward_amount = core_contract
+            .call("get_vapi_reward_amount")
+            .args_json(json!({"vapi_id": vapi_id, "account_id": account_id}))
+            .view()
+            .await?
+            .json::<u128>()?;
+        assert_eq!(vapi_reward_amount, reward_amount.as_yoctonear());
+    }
+
+    let res = alice
+        .call(core_contract.id(), "claim_vapi_reward")
+        .args_json(json!({"vapi_id": vapi_id}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let alice_balance = ft_contract
+        .call("ft_balance_of")
+        .args_json(json!({"account_id": alice.id()}))
+        .view()
+        .await?
+        .json::<U128>()?;
+    assert_eq!(alice_balance, U128::from(NearToken::from_near(6).as_yoctonear()));
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn test_vapi_nft_transfer_call() -> anyhow::Result<()> {
+    let initial_balance = U128::from(NearToken::from_near(10000).as_yoctonear());
+    let worker = near_workspaces::sandbox().await?;
+    let (_ft_contract, owner, core_contract) = init(&worker, initial_balance).await?;
+    deposit_storage(&core_contract, owner.id(), NearToken::from_millinear(100)).await?;
+
+    let users = create_users(&worker, vec!["alice", "bob"], vec![10, 10]).await?;
+    let alice = users.first().unwrap().clone();
+    let bob = users.get(1).unwrap().clone();
+    for user in [&alice, &bob] {
+        deposit_storage(&core_contract, user.id(), NearToken::from_millinear(100)).await?;
+    }
+
+    let vapi_id = "test-vapi";
+    let res = alice
+        .call(core_contract.id(), "create_vapi")
+        .args_json(json!({"vapi_id": vapi_id}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    // The coder transfers the VAPI with the standard method
+    let res = alice
+        .call(core_contract.id(), "nft_transfer")
+        .args_json(json!({"receiver_id": bob.id(), "token_id": vapi_id}))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    // Only the coder can manage the VAPI
+    for (user, is_coder) in [(&alice, false), (&bob, true)] {
+        let res = user
+            .call(core_contract.id(), "set_contributors")
+            .args_json(json!({"vapi_id": vapi_id, "account_ids": [owner.id()], "share_bps": [1000]}))
+            .max_gas()
+            .transact()
+            .await?;
+        assert_eq!(res.is_success(), is_coder);
+    }
+
+    // A receiver without `nft_on_transfer` doesn't keep the VAPI, the token and the coder ownership go back to bob
+    let res = bob
+        .call(core_contract.id(), "nft_transfer_call")
+        .args_json(json!({"receiver_id": alice.id(), "token_id": vapi_id, "msg": ""}))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    res.logs().iter().for_each(|log| println!("{:?}", log));
+    assert!(res.is_success());
+    assert!(!res.json::<bool>()?);
+
+    let token = core_contract
+        .call("nft_token")
+        .args_json(json!({"token_id": vapi_id}))
+        .view()
+        .await?
+        .json::<Option<Token>>()?
+        .unwrap();
+    assert_eq!(token.owner_id.as_str(), bob.id().as_str());
+
+    let res = bob
+        .call(core_contract.id(), "set_contributors")
+        .args_json(json!({"vapi_id": vapi_id, "account_ids": [], "share_bps": []}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    return Ok(());
+}
@@ -0,0 +1,266 @@
+#![allow(clippy::needless_return)]
+
+use near_contract_standards::storage_management::StorageBalance;
+use near_crypto::SecretKey;
+use near_sdk::{borsh, json_types::U128, NearToken};
+use near_workspaces::{Account, Contract, DevNetwork, Worker};
+use serde_json::json;
+use ticle_core::consumer::{ConsumerInfoResponse, ConsumerVAPIInfo, UsageVoucher};
+
+use crate::common::utils::*;
+pub mod common;
+
+async fn init_consumer(worker: &Worker<impl DevNetwork>) -> anyhow::Result<(Contract, Account, Contract, Account, Account)> {
+    let initial_balance = U128::from(NearToken::from_near(10000).as_yoctonear());
+    let (ft_contract, owner, core_contract) = init(worker, initial_balance).await?;
+
+    register_user(&ft_contract, core_contract.id()).await?;
+
+    let users = create_users(worker, vec!["coder", "consumer"], vec![10, 10]).await?;
+    for user in users.iter() {
+        register_user(&ft_contract, user.id()).await?;
+        deposit_storage(&core_contract, user.id(), NearToken::from_millinear(100)).await?;
+
+        let res = owner
+            .call(ft_contract.id(), "ft_transfer")
+            .args_json((user.id(), U128::from(NearToken::from_near(100).as_yoctonear()), "transfer to test account"))
+            .max_gas()
+            .deposit(ONE_YOCTO)
+            .transact()
+            .await?;
+        assert!(res.is_success());
+    }
+
+    let coder = users.first().unwrap().clone();
+    let consumer = users.get(1).unwrap().clone();
+
+    let vapi_id = "test-vapi";
+    let res = coder
+        .call(core_contract.id(), "create_vapi")
+        .args_json(json!({"vapi_id": vapi_id}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let res = consumer
+        .call(ft_contract.id(), "ft_transfer_call")
+        .args_json((core_contract.id(), U128::from(NearToken::from_near(50).as_yoctonear()), Option::<String>::None, json!({ "action": "fund_consumer" }).to_string()))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+    assert_eq!(res.json::<U128>()?, U128::from(NearToken::from_near(50).as_yoctonear()));
+
+    return Ok((ft_contract, owner, core_contract, coder, consumer));
+}
+
+fn signed_voucher(core_contract: &Contract, signer: &Account, consumer: &Account, vapi_id: &str, cumulative_amount: NearToken, nonce: u64) -> serde_json::Value {
+    let signer_secret_key: SecretKey = signer.secret_key().to_string().parse().unwrap();
+    let voucher = UsageVoucher {
+        contract_id: core_contract.id().as_str().parse().unwrap(),
+        consumer_id: consumer.id().as_str().parse().unwrap(),
+        vapi_id: vapi_id.to_string(),
+        cumulative_amount: U128(cumulative_amount.as_yoctonear()),
+        nonce,
+    };
+    let signature = signer_secret_key.sign(&borsh::to_vec(&voucher).unwrap());
+    return json!({
+        "voucher": voucher,
+        "signatures": [{ "public_key": signer_secret_key.public_key().to_string(), "signature": signature.to_string() }],
+    });
+}
+
+async fn storage_balance_of(core_contract: &Contract, account: &Account) -> anyhow::Result<StorageBalance> {
+    return Ok(core_contract
+        .call("storage_balance_of")
+        .args_json(json!({"account_id": account.id()}))
+        .view()
+        .await?
+        .json::<Option<StorageBalance>>()?
+        .unwrap());
+}
+
+#[tokio::test]
+async fn test_redeem_voucher() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let (_ft_contract, owner, core_contract, coder, consumer) = init_consumer(&worker).await?;
+    let vapi_id = "test-vapi";
+
+    // The owner's key is the initial signer
+    let signed_voucher = |cumulative_amount: NearToken, nonce: u64| signed_voucher(&core_contract, &owner, &consumer, vapi_id, cumulative_amount, nonce);
+
+    // Anyone can redeem a voucher, only the usage on top of the last redeemed voucher is debited
+    for (cumulative_amount, nonce) in [(NearToken::from_near(10), 1), (NearToken::from_near(25), 2)] {
+        let res = coder
+            .call(core_contract.id(), "redeem_voucher")
+            .args_json(signed_voucher(cumulative_amount, nonce))
+            .max_gas()
+            .transact()
+            .await?;
+        res.logs().iter().for_each(|log| println!("{:?}", log));
+        assert!(res.is_success());
+    }
+
+    let consumer_balance = core_contract
+        .call("get_consumer_balance")
+        .args_json(json!({"consumer_id": consumer.id()}))
+        .view()
+        .await?
+        .json::<u128>()?;
+    assert_eq!(consumer_balance, NearToken::from_near(25).as_yoctonear());
+
+    let consumer_vapi_info = core_contract
+        .call("get_consumer_vapi_info")
+        .args_json(json!({"consumer_id": consumer.id(), "vapi_id": vapi_id}))
+        .view()
+        .await?
+        .json::<Option<ConsumerVAPIInfo>>()?
+        .unwrap();
+    assert_eq!(consumer_vapi_info.redeemed_amount, NearToken::from_near(25).as_yoctonear());
+    assert_eq!(consumer_vapi_info.nonce, 2);
+
+    let total_burned_amount = core_contract
+        .call("get_total_burned_amount")
+        .view()
+        .await?
+        .json::<u128>()?;
+    assert_eq!(total_burned_amount, NearToken::from_millinear(250).as_yoctonear());
+
+    // Replayed and tampered vouchers are rejected
+    let res = coder
+        .call(core_contract.id(), "redeem_voucher")
+        .args_json(signed_voucher(NearToken::from_near(25), 2))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_failure());
+
+    let mut tampered_voucher = signed_voucher(NearToken::from_near(30), 3);
+    tampered_voucher["voucher"]["cumulative_amount"] = json!(U128(NearToken::from_near(40).as_yoctonear()));
+    let res = coder
+        .call(core_contract.id(), "redeem_voucher")
+        .args_json(tampered_voucher)
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_failure());
+
+    // A voucher issued for another deployment is refused
+    let mut foreign_voucher = signed_voucher(NearToken::from_near(30), 3);
+    foreign_voucher["voucher"]["contract_id"] = json!(consumer.id());
+    let res = coder
+        .call(core_contract.id(), "redeem_voucher")
+        .args_json(foreign_voucher)
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_failure());
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn test_consumer_credit() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let (_ft_contract, owner, core_contract, coder, consumer) = init_consumer(&worker).await?;
+    let vapi_id = "test-vapi";
+
+    let res = consumer
+        .call(core_contract.id(), "set_spending_limit")
+        .args_json(json!({"vapi_id": vapi_id, "spending_limit": U128(NearToken::from_near(20).as_yoctonear())}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    // Vouchers over the spending limit are rejected
+    let res = coder
+        .call(core_contract.id(), "redeem_voucher")
+        .args_json(signed_voucher(&core_contract, &owner, &consumer, vapi_id, NearToken::from_near(21), 1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_failure());
+
+    let res = consumer
+        .call(core_contract.id(), "consumer_request_withdrawal")
+        .args_json(json!({"amount": U128(NearToken::from_near(40).as_yoctonear())}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    // Outstanding usage is still redeemable from the credit during the notice period
+    let res = coder
+        .call(core_contract.id(), "redeem_voucher")
+        .args_json(signed_voucher(&core_contract, &owner, &consumer, vapi_id, NearToken::from_near(15), 1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let consumer_info = core_contract
+        .call("get_consumer_info")
+        .args_json(json!({"consumer_id": consumer.id()}))
+        .view()
+        .await?
+        .json::<Option<ConsumerInfoResponse>>()?
+        .unwrap();
+    assert_eq!(consumer_info.balance, 0);
+    assert_eq!(consumer_info.withdrawing_amount, NearToken::from_near(35).as_yoctonear());
+    assert_eq!(consumer_info.vapis.len(), 1);
+    assert_eq!(consumer_info.vapis[0].1.spending_limit, Some(NearToken::from_near(20).as_yoctonear()));
+
+    let res = consumer
+        .call(core_contract.id(), "consumer_claim_withdrawal")
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_failure());
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn test_redeem_voucher_without_consumer_storage() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let (_ft_contract, owner, core_contract, coder, consumer) = init_consumer(&worker).await?;
+    let vapi_id = "test-vapi";
+
+    // The consumer withdraws all of the storage deposit not used yet
+    let res = consumer
+        .call(core_contract.id(), "storage_withdraw")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    assert_eq!(storage_balance_of(&core_contract, &consumer).await?.available, NearToken::from_yoctonear(0));
+    let coder_storage_balance = storage_balance_of(&core_contract, &coder).await?;
+
+    // The usage record of the VAPI is paid by the caller
+    let res = coder
+        .call(core_contract.id(), "redeem_voucher")
+        .args_json(signed_voucher(&core_contract, &owner, &consumer, vapi_id, NearToken::from_near(10), 1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    assert_eq!(storage_balance_of(&core_contract, &consumer).await?.available, NearToken::from_yoctonear(0));
+    assert!(storage_balance_of(&core_contract, &coder).await?.available < coder_storage_balance.available);
+
+    let consumer_balance = core_contract
+        .call("get_consumer_balance")
+        .args_json(json!({"consumer_id": consumer.id()}))
+        .view()
+        .await?
+        .json::<u128>()?;
+    assert_eq!(consumer_balance, NearToken::from_near(40).as_yoctonear());
+
+    return Ok(());
+}
@@ -0,0 +1,82 @@
+#![allow(clippy::needless_return)]
+
+pub mod liquid;
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_contract_standards::fungible_token::metadata::FungibleTokenMetadata;
+    use near_contract_standards::fungible_token::FungibleTokenCore;
+    use near_sdk::json_types::U128;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::{testing_env, AccountId, NearToken, PromiseResult, RuntimeFeesConfig};
+
+    fn new_contract() -> liquid::LiquidTokenContract {
+        testing_env!(VMContextBuilder::new().predecessor_account_id(accounts(0)).build());
+        let metadata = FungibleTokenMetadata {
+            spec: "ft-1.0.0".to_string(),
+            name: "Liquid Staked TICLE".to_string(),
+            symbol: "stTIC".to_string(),
+            icon: None,
+            reference: None,
+            reference_hash: None,
+            decimals: 24,
+        };
+        return liquid::LiquidTokenContract::new(accounts(0), accounts(1), metadata);
+    }
+
+    fn set_predecessor(predecessor_account_id: AccountId, attached_deposit: u128) {
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(predecessor_account_id)
+            .attached_deposit(NearToken::from_yoctonear(attached_deposit))
+            .build());
+    }
+
+    #[test]
+    fn test_mint_registers_staker() {
+        let mut contract = new_contract();
+        contract.mint(accounts(2), U128(1000));
+        contract.mint(accounts(2), U128(500));
+        assert_eq!(contract.ft_balance_of(accounts(2)), U128(1500));
+        assert_eq!(contract.ft_total_supply(), U128(1500));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only core can mint")]
+    fn test_mint_only_core() {
+        let mut contract = new_contract();
+        set_predecessor(accounts(2), 0);
+        contract.mint(accounts(2), U128(1000));
+    }
+
+    #[test]
+    fn test_redeem_burns_shares() {
+        let mut contract = new_contract();
+        contract.mint(accounts(2), U128(1000));
+
+        set_predecessor(accounts(2), 1);
+        contract.redeem(U128(400));
+        assert_eq!(contract.ft_balance_of(accounts(2)), U128(600));
+        assert_eq!(contract.ft_total_supply(), U128(600));
+    }
+
+    #[test]
+    fn test_failed_redeem_restores_shares() {
+        let mut contract = new_contract();
+        contract.mint(accounts(2), U128(1000));
+
+        set_predecessor(accounts(2), 1);
+        contract.redeem(U128(400));
+
+        testing_env!(
+            VMContextBuilder::new().predecessor_account_id(accounts(0)).current_account_id(accounts(0)).build(),
+            near_sdk::test_vm_config(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed],
+        );
+        assert_eq!(contract.callback_redeem(&accounts(2), U128(400)), U128(0));
+        assert_eq!(contract.ft_balance_of(accounts(2)), U128(1000));
+    }
+}
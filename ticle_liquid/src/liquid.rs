@@ -0,0 +1,198 @@
+use near_contract_standards::fungible_token::events::{FtBurn, FtMint};
+use near_contract_standards::fungible_token::metadata::{FungibleTokenMetadata, FungibleTokenMetadataProvider};
+use near_contract_standards::fungible_token::{FungibleToken, FungibleTokenCore, FungibleTokenResolver};
+use near_contract_standards::storage_management::{StorageBalance, StorageBalanceBounds, StorageManagement};
+use near_sdk::collections::LazyOption;
+use near_sdk::json_types::U128;
+use near_sdk::{assert_one_yocto, env, ext_contract, log, near, require, AccountId, BorshStorageKey, Gas, NearToken, PanicOnDefault, Promise, PromiseOrValue, PromiseResult};
+
+#[derive(BorshStorageKey)]
+#[near]
+enum StorageKey {
+    FungibleToken,
+    Metadata,
+}
+
+#[ext_contract(ext_ticle_core)]
+pub trait TicleCore {
+    fn redeem_liquid_shares(&mut self, reviewer_id: AccountId, account_id: AccountId, shares: U128) -> U128;
+}
+
+/// Liquid shares of the delegation to `reviewer_id` pooled in the core contract.
+/// The core contract holds the exchange rate, mints the shares of each stake and prices redemptions.
+#[near(contract_state)]
+#[derive(PanicOnDefault)]
+pub struct LiquidTokenContract {
+    pub token: FungibleToken,
+    pub metadata: LazyOption<FungibleTokenMetadata>,
+    pub core_id: AccountId,
+    pub reviewer_id: AccountId,
+}
+
+#[near]
+impl LiquidTokenContract {
+    #[init]
+    pub fn new(core_id: AccountId, reviewer_id: AccountId, metadata: FungibleTokenMetadata) -> Self {
+        metadata.assert_valid();
+        return Self {
+            token: FungibleToken::new(StorageKey::FungibleToken),
+            metadata: LazyOption::new(StorageKey::Metadata, Some(&metadata)),
+            core_id,
+            reviewer_id,
+        };
+    }
+
+    /// Mints the shares of a stake, only callable by the core contract.
+    /// Stakers not registered yet are registered at the expense of this contract.
+    pub fn mint(&mut self, account_id: AccountId, amount: U128) {
+        require!(env::predecessor_account_id() == self.core_id, "Only core can mint");
+        if !self.token.accounts.contains_key(&account_id) {
+            self.token.internal_register_account(&account_id);
+        }
+
+        self.token.internal_deposit(&account_id, amount.into());
+        FtMint {
+            owner_id: &account_id,
+            amount,
+            memo: Some("Liquid stake"),
+        }.emit();
+    }
+
+    /// Burns `amount` shares of the caller and redeems them in the core contract, which starts
+    /// the unbonding of their value. The refund is claimed from the core contract once unbonded.
+    #[payable]
+    pub fn redeem(&mut self, amount: U128) -> Promise {
+        assert_one_yocto();
+        require!(amount.0 > 0, "The amount should be a positive number");
+        let account_id = env::predecessor_account_id();
+
+        self.token.internal_withdraw(&account_id, amount.into());
+        FtBurn {
+            owner_id: &account_id,
+            amount,
+            memo: Some("Liquid redeem"),
+        }.emit();
+
+        return ext_ticle_core::ext(self.core_id.clone())
+            .with_static_gas(Gas::from_tgas(50))
+            .redeem_liquid_shares(self.reviewer_id.clone(), account_id.clone(), amount)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(Gas::from_tgas(10))
+                    .callback_redeem(&account_id, amount)
+            );
+    }
+
+    /// Restores the burned shares when the core contract refused the redemption
+    #[private]
+    pub fn callback_redeem(&mut self, account_id: &AccountId, amount: U128) -> U128 {
+        const REDEEM_PROMISE_INDEX: u64 = 0;
+        if let PromiseResult::Successful(value) = env::promise_result(REDEEM_PROMISE_INDEX) {
+            return near_sdk::serde_json::from_slice::<U128>(&value).unwrap_or(U128(0));
+        }
+
+        log!("[callback_redeem] redeem failed, restoring {} shares of {}", amount.0, account_id);
+        if !self.token.accounts.contains_key(account_id) {
+            self.token.internal_register_account(account_id);
+        }
+        self.token.internal_deposit(account_id, amount.into());
+        FtMint {
+            owner_id: account_id,
+            amount,
+            memo: Some("Liquid redeem refund"),
+        }.emit();
+        return U128(0);
+    }
+
+    pub fn get_core_id(&self) -> AccountId {
+        return self.core_id.clone();
+    }
+
+    pub fn get_reviewer_id(&self) -> AccountId {
+        return self.reviewer_id.clone();
+    }
+}
+
+#[near]
+impl FungibleTokenCore for LiquidTokenContract {
+    #[payable]
+    fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>) {
+        self.token.ft_transfer(receiver_id, amount, memo)
+    }
+
+    #[payable]
+    fn ft_transfer_call(
+        &mut self,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: Option<String>,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        self.token.ft_transfer_call(receiver_id, amount, memo, msg)
+    }
+
+    fn ft_total_supply(&self) -> U128 {
+        self.token.ft_total_supply()
+    }
+
+    fn ft_balance_of(&self, account_id: AccountId) -> U128 {
+        self.token.ft_balance_of(account_id)
+    }
+}
+
+#[near]
+impl FungibleTokenResolver for LiquidTokenContract {
+    #[private]
+    fn ft_resolve_transfer(
+        &mut self,
+        sender_id: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+    ) -> U128 {
+        let (used_amount, burned_amount) = self.token.internal_ft_resolve_transfer(&sender_id, receiver_id, amount);
+        if burned_amount > 0 {
+            log!("Account @{} burned {}", sender_id, burned_amount);
+        }
+        used_amount.into()
+    }
+}
+
+#[near]
+impl StorageManagement for LiquidTokenContract {
+    #[payable]
+    fn storage_deposit(
+        &mut self,
+        account_id: Option<AccountId>,
+        registration_only: Option<bool>,
+    ) -> StorageBalance {
+        self.token.storage_deposit(account_id, registration_only)
+    }
+
+    #[payable]
+    fn storage_withdraw(&mut self, amount: Option<NearToken>) -> StorageBalance {
+        self.token.storage_withdraw(amount)
+    }
+
+    /// Shares can't be burned by unregistering, they have to be redeemed
+    #[payable]
+    fn storage_unregister(&mut self, force: Option<bool>) -> bool {
+        assert_one_yocto();
+        require!(!force.unwrap_or(false), "Shares have to be redeemed before unregistering");
+        self.token.internal_storage_unregister(None).is_some()
+    }
+
+    fn storage_balance_bounds(&self) -> StorageBalanceBounds {
+        self.token.storage_balance_bounds()
+    }
+
+    fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance> {
+        self.token.storage_balance_of(account_id)
+    }
+}
+
+#[near]
+impl FungibleTokenMetadataProvider for LiquidTokenContract {
+    fn ft_metadata(&self) -> FungibleTokenMetadata {
+        self.metadata.get().unwrap()
+    }
+}
@@ -0,0 +1,41 @@
+#![no_std]
+#![allow(clippy::needless_return)]
+
+mod u256 {
+    #![allow(clippy::assign_op_pattern, clippy::manual_div_ceil)]
+    uint::construct_uint! {
+        /// Intermediate of balance math, 24 decimal amounts times shares, rates or durations overflow u128
+        pub struct U256(4);
+    }
+}
+pub use u256::U256;
+
+/// `a * b / c` without overflowing the intermediate product, `None` when `c` is zero or the result exceeds u128
+pub fn mul_div(a: u128, b: u128, c: u128) -> Option<u128> {
+    if c == 0 {
+        return None;
+    }
+    let result = U256::from(a) * U256::from(b) / U256::from(c);
+    if result > U256::from(u128::MAX) {
+        return None;
+    }
+    return Some(result.as_u128());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ONE_TIC: u128 = 1_000_000_000_000_000_000_000_000;
+
+    #[test]
+    fn test_mul_div() {
+        assert_eq!(mul_div(10_000_000 * ONE_TIC, 10_000_000 * ONE_TIC, 10_000_000 * ONE_TIC), Some(10_000_000 * ONE_TIC));
+        assert_eq!(mul_div(u128::MAX, 3, 2), None);
+        assert_eq!(mul_div(1, 1, 0), None);
+
+        // Reward per share is scaled by 1e12, deposit times reward per share overflows u128 here
+        let acc_reward_per_share = mul_div(10_000 * ONE_TIC, 1_000_000_000_000, 100 * ONE_TIC).unwrap();
+        assert_eq!(mul_div(100 * ONE_TIC, acc_reward_per_share, 1_000_000_000_000), Some(10_000 * ONE_TIC));
+    }
+}
@@ -0,0 +1,97 @@
+use near_contract_standards::fungible_token::Balance;
+use near_sdk::json_types::U128;
+use near_sdk::{assert_one_yocto, env, near, require, AccountId};
+
+use crate::events::TokenEvent;
+use crate::token::{TokenContract, TokenContractExt};
+
+#[near(serializers = [borsh, json])]
+pub struct Allowance {
+    pub amount: U128,
+    /// Timestamp in milliseconds after which the allowance can't be spent
+    pub expires_at: Option<u64>,
+}
+
+impl Allowance {
+    pub fn is_expired(&self) -> bool {
+        return self.expires_at.is_some_and(|expires_at| env::block_timestamp_ms() >= expires_at);
+    }
+}
+
+impl TokenContract {
+    pub(crate) fn internal_spend_allowance(&mut self, owner_id: &AccountId, spender_id: &AccountId, amount: Balance) {
+        let key = (owner_id.clone(), spender_id.clone());
+        let mut allowance = self.allowances.get(&key).expect("No allowance for the spender");
+        require!(!allowance.is_expired(), "Allowance expired");
+        require!(allowance.amount.0 >= amount, "Transfer amount exceeds the allowance");
+
+        allowance.amount = (allowance.amount.0 - amount).into();
+        self.allowances.insert(&key, &allowance);
+    }
+
+    /// Counts the transfer and burn allowances granted by `owner_id`, an account can't be
+    /// unregistered while any of them is left
+    pub(crate) fn internal_update_allowance_count(&mut self, owner_id: &AccountId, existed: bool, exists: bool) {
+        let count = self.allowance_counts.get(owner_id).unwrap_or(0);
+        let count = match (existed, exists) {
+            (false, true) => count + 1,
+            (true, false) => count - 1,
+            _ => count,
+        };
+
+        if count == 0 {
+            self.allowance_counts.remove(owner_id);
+        } else {
+            self.allowance_counts.insert(owner_id, &count);
+        }
+    }
+}
+
+#[near]
+impl TokenContract {
+    /// Allows `spender_id` to transfer up to `amount` from the caller until `expires_at` (ms),
+    /// zero revokes the allowance. The deposit attached to a new allowance pays for its storage.
+    #[payable]
+    pub fn ft_approve(&mut self, spender_id: AccountId, amount: U128, expires_at: Option<u64>) {
+        let owner_id = env::predecessor_account_id();
+        require!(env::attached_deposit().as_yoctonear() > 0, "Requires attached deposit of at least 1 yoctoNEAR");
+        require!(owner_id != spender_id, "Can't approve the owner");
+        require!(expires_at.is_none_or(|expires_at| expires_at > env::block_timestamp_ms()), "Expiration must be in the future");
+
+        let key = (owner_id.clone(), spender_id.clone());
+        let initial_storage_usage = env::storage_usage();
+        let existed = if amount.0 == 0 {
+            self.allowances.remove(&key).is_some()
+        } else {
+            self.allowances.insert(&key, &Allowance { amount, expires_at }).is_some()
+        };
+        self.internal_update_allowance_count(&owner_id, existed, amount.0 > 0);
+        self.internal_settle_storage(initial_storage_usage);
+
+        TokenEvent::FtApprove { owner_id, spender_id, amount, expires_at }.emit();
+    }
+
+    /// Transfers `amount` from `owner_id` to `receiver_id` out of the allowance granted to the caller
+    #[payable]
+    pub fn ft_transfer_from(&mut self, owner_id: AccountId, receiver_id: AccountId, amount: U128, memo: Option<String>) {
+        assert_one_yocto();
+        let spender_id = env::predecessor_account_id();
+        self.assert_not_frozen(&owner_id);
+        self.assert_not_frozen(&receiver_id);
+        self.internal_spend_allowance(&owner_id, &spender_id, amount.into());
+        self.token.internal_transfer(&owner_id, &receiver_id, amount.into(), memo);
+        self.internal_checkpoint(&[&owner_id, &receiver_id]);
+    }
+
+    /// Spendable allowance, zero once expired
+    pub fn ft_allowance(&self, owner_id: AccountId, spender_id: AccountId) -> U128 {
+        return match self.allowances.get(&(owner_id, spender_id)) {
+            Some(allowance) if !allowance.is_expired() => allowance.amount,
+            _ => U128(0),
+        };
+    }
+
+    pub fn ft_allowance_info(&self, owner_id: AccountId, spender_id: AccountId) -> Option<Allowance> {
+        return self.allowances.get(&(owner_id, spender_id));
+    }
+}
@@ -0,0 +1,95 @@
+use near_contract_standards::fungible_token::receiver::ext_ft_receiver;
+use near_contract_standards::fungible_token::resolver::ext_ft_resolver;
+use near_sdk::json_types::U128;
+use near_sdk::{assert_one_yocto, env, near, require, serde_json, AccountId, Gas, Promise, PromiseResult};
+
+use crate::token::{TokenContract, TokenContractExt};
+
+const GAS_FOR_RESOLVE_TRANSFER: Gas = Gas::from_tgas(5);
+const GAS_FOR_RECEIVER_CALL: Gas = Gas::from_tgas(30);
+const GAS_FOR_RESOLVE_BATCH: Gas = Gas::from_tgas(10);
+/// Each receiver needs `GAS_FOR_RECEIVER_CALL` and `GAS_FOR_RESOLVE_TRANSFER`, more don't fit in 300 Tgas
+pub const MAX_BATCH_TRANSFER_CALL_RECEIVERS: usize = 8;
+
+impl TokenContract {
+    /// Moves every amount out of the caller's balance, panicking before any transfer when
+    /// a receiver is frozen or not registered so the batch is all or nothing
+    fn internal_batch_transfer(&mut self, receiver_ids: &[AccountId], amounts: &[U128], memo: Option<String>) -> AccountId {
+        require!(!receiver_ids.is_empty(), "Receivers must not be empty");
+        require!(receiver_ids.len() == amounts.len(), "Receivers and amounts must have the same length");
+
+        let sender_id = env::predecessor_account_id();
+        self.assert_not_frozen(&sender_id);
+        for (index, receiver_id) in receiver_ids.iter().enumerate() {
+            self.assert_not_frozen(receiver_id);
+            require!(self.token.accounts.contains_key(receiver_id), format!("Receiver {} at index {} is not registered", receiver_id, index));
+        }
+
+        for (receiver_id, amount) in receiver_ids.iter().zip(amounts) {
+            self.token.internal_transfer(&sender_id, receiver_id, amount.0, memo.clone());
+        }
+
+        let mut account_ids: Vec<&AccountId> = receiver_ids.iter().collect();
+        account_ids.push(&sender_id);
+        self.internal_checkpoint(&account_ids);
+
+        return sender_id;
+    }
+}
+
+#[near]
+impl TokenContract {
+    /// Transfers `amounts[i]` to `receiver_ids[i]` in one call, emitting an `ft_transfer` event per receiver
+    #[payable]
+    pub fn ft_batch_transfer(&mut self, receiver_ids: Vec<AccountId>, amounts: Vec<U128>, memo: Option<String>) {
+        assert_one_yocto();
+        self.internal_batch_transfer(&receiver_ids, &amounts, memo);
+    }
+
+    /// `ft_transfer_call` to up to `MAX_BATCH_TRANSFER_CALL_RECEIVERS` receivers, each one is resolved
+    /// independently and the result lists the used amount per receiver in the order of `receiver_ids`
+    #[payable]
+    pub fn ft_batch_transfer_call(&mut self, receiver_ids: Vec<AccountId>, amounts: Vec<U128>, memo: Option<String>, msg: String) -> Promise {
+        assert_one_yocto();
+        require!(
+            receiver_ids.len() <= MAX_BATCH_TRANSFER_CALL_RECEIVERS,
+            format!("At most {} receivers can be called in one batch", MAX_BATCH_TRANSFER_CALL_RECEIVERS)
+        );
+        let required_gas = GAS_FOR_RECEIVER_CALL.saturating_add(GAS_FOR_RESOLVE_TRANSFER).saturating_mul(receiver_ids.len() as u64).saturating_add(GAS_FOR_RESOLVE_BATCH);
+        require!(env::prepaid_gas() > required_gas, "More gas is required");
+
+        let sender_id = self.internal_batch_transfer(&receiver_ids, &amounts, memo);
+
+        let transfer_promise = receiver_ids
+            .into_iter()
+            .zip(amounts)
+            .map(|(receiver_id, amount)| {
+                ext_ft_receiver::ext(receiver_id.clone())
+                    .with_static_gas(GAS_FOR_RECEIVER_CALL)
+                    .ft_on_transfer(sender_id.clone(), amount, msg.clone())
+                    .then(
+                        ext_ft_resolver::ext(env::current_account_id())
+                            .with_static_gas(GAS_FOR_RESOLVE_TRANSFER)
+                            .ft_resolve_transfer(sender_id.clone(), receiver_id, amount)
+                    )
+            })
+            .reduce(|joined, promise| joined.and(promise))
+            .unwrap();
+
+        return transfer_promise.then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_RESOLVE_BATCH)
+                .callback_batch_transfer_call()
+        );
+    }
+
+    #[private]
+    pub fn callback_batch_transfer_call(&self) -> Vec<U128> {
+        return (0..env::promise_results_count())
+            .map(|index| match env::promise_result(index) {
+                PromiseResult::Successful(value) => serde_json::from_slice::<U128>(&value).unwrap_or(U128(0)),
+                PromiseResult::Failed => U128(0),
+            })
+            .collect();
+    }
+}
@@ -0,0 +1,75 @@
+use near_contract_standards::fungible_token::events::FtBurn;
+use near_contract_standards::fungible_token::Balance;
+use near_sdk::json_types::U128;
+use near_sdk::{assert_one_yocto, env, near, require, AccountId};
+
+use crate::events::TokenEvent;
+use crate::token::{TokenContract, TokenContractExt};
+
+impl TokenContract {
+    pub(crate) fn internal_burn(&mut self, account_id: &AccountId, amount: Balance, memo: Option<String>) {
+        require!(amount > 0, "The amount should be a positive number");
+        self.assert_not_frozen(account_id);
+        self.token.internal_withdraw(account_id, amount);
+        self.total_burned += amount;
+        self.internal_checkpoint(&[account_id]);
+
+        FtBurn {
+            owner_id: account_id,
+            amount: amount.into(),
+            memo: memo.as_deref(),
+        }.emit();
+    }
+}
+
+#[near]
+impl TokenContract {
+    /// Burns `amount` from the caller
+    #[payable]
+    pub fn burn(&mut self, amount: U128, memo: Option<String>) {
+        assert_one_yocto();
+        self.internal_burn(&env::predecessor_account_id(), amount.into(), memo);
+    }
+
+    /// Burns `amount` from `owner_id` out of the allowance the owner granted to the caller with `approve_burn`
+    #[payable]
+    pub fn burn_from(&mut self, owner_id: AccountId, amount: U128, memo: Option<String>) {
+        assert_one_yocto();
+        let spender_id = env::predecessor_account_id();
+        let key = (owner_id.clone(), spender_id);
+        let allowance = self.burn_allowances.get(&key).unwrap_or(0);
+        require!(allowance >= amount.0, "Burn amount exceeds the allowance");
+
+        self.burn_allowances.insert(&key, &(allowance - amount.0));
+        self.internal_burn(&owner_id, amount.into(), memo);
+    }
+
+    /// Allows `spender_id` to burn up to `amount` from the caller, zero revokes the allowance.
+    /// The deposit attached to a new allowance pays for its storage.
+    #[payable]
+    pub fn approve_burn(&mut self, spender_id: AccountId, amount: U128) {
+        let owner_id = env::predecessor_account_id();
+        require!(env::attached_deposit().as_yoctonear() > 0, "Requires attached deposit of at least 1 yoctoNEAR");
+        require!(owner_id != spender_id, "Can't approve the owner");
+
+        let key = (owner_id.clone(), spender_id.clone());
+        let initial_storage_usage = env::storage_usage();
+        let existed = if amount.0 == 0 {
+            self.burn_allowances.remove(&key).is_some()
+        } else {
+            self.burn_allowances.insert(&key, &amount.0).is_some()
+        };
+        self.internal_update_allowance_count(&owner_id, existed, amount.0 > 0);
+        self.internal_settle_storage(initial_storage_usage);
+
+        TokenEvent::BurnApprove { owner_id, spender_id, amount }.emit();
+    }
+
+    pub fn burn_allowance(&self, owner_id: AccountId, spender_id: AccountId) -> U128 {
+        return self.burn_allowances.get(&(owner_id, spender_id)).unwrap_or(0).into();
+    }
+
+    pub fn ft_total_burned(&self) -> U128 {
+        return self.total_burned.into();
+    }
+}
@@ -0,0 +1,173 @@
+use near_contract_standards::fungible_token::Balance;
+use near_sdk::collections::Vector;
+use near_sdk::json_types::U128;
+use near_sdk::{assert_one_yocto, env, near, require, AccountId, IntoStorageKey, StorageUsage};
+
+use crate::events::TokenEvent;
+use crate::token::{StorageKey, TokenContract, TokenContractExt};
+
+/// Balance as of the end of `block_height`
+#[near(serializers = [borsh])]
+pub struct Checkpoint {
+    pub block_height: u64,
+    pub balance: Balance,
+}
+
+/// Balance changes in block order. Between two snapshots only the latest change is kept, so the history
+/// grows by at most one checkpoint per snapshot however often the balance changes.
+#[near(serializers = [borsh])]
+pub struct CheckpointHistory {
+    checkpoints: Vector<Checkpoint>,
+}
+
+impl CheckpointHistory {
+    pub fn new<S: IntoStorageKey>(prefix: S) -> Self {
+        return Self { checkpoints: Vector::new(prefix) };
+    }
+
+    fn write(&mut self, balance: Balance, last_snapshot: Option<u64>) {
+        let checkpoint = Checkpoint {
+            block_height: env::block_height(),
+            balance,
+        };
+
+        let len = self.checkpoints.len();
+        if len == 0 && balance == 0 {
+            return;
+        }
+        if len > 0 {
+            let last = self.checkpoints.get(len - 1).unwrap();
+            if last.balance == balance {
+                return;
+            }
+            // The last checkpoint is only needed as the balance of a snapshot taken at or after its block
+            if last.block_height == checkpoint.block_height || last_snapshot.is_none_or(|snapshot| last.block_height > snapshot) {
+                self.checkpoints.replace(len - 1, &checkpoint);
+                return;
+            }
+        }
+        self.checkpoints.push(&checkpoint);
+    }
+
+    fn balance_at(&self, block_height: u64) -> Balance {
+        // Binary search for the last checkpoint at or before `block_height`
+        let (mut low, mut high) = (0, self.checkpoints.len());
+        while low < high {
+            let mid = (low + high) / 2;
+            if self.checkpoints.get(mid).unwrap().block_height <= block_height {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+
+        if low == 0 {
+            return 0;
+        }
+        return self.checkpoints.get(low - 1).unwrap().balance;
+    }
+
+    pub fn clear(&mut self) {
+        self.checkpoints.clear();
+    }
+}
+
+impl TokenContract {
+    fn account_checkpoints(&self, account_id: &AccountId) -> CheckpointHistory {
+        return self.balance_checkpoints.get(account_id).unwrap_or_else(|| {
+            CheckpointHistory::new(StorageKey::AccountCheckpoints { account_hash: env::sha256(account_id.as_bytes()) })
+        });
+    }
+
+    fn last_snapshot(&self) -> Option<u64> {
+        return self.snapshots.len().checked_sub(1).and_then(|index| self.snapshots.get(index));
+    }
+
+    fn assert_snapshot(&self, block_height: u64) {
+        require!(block_height < env::block_height(), "Block height must be in the past");
+        // Binary search for the first snapshot at or after `block_height`, snapshots are taken in block order
+        let (mut low, mut high) = (0, self.snapshots.len());
+        while low < high {
+            let mid = (low + high) / 2;
+            if self.snapshots.get(mid).unwrap() < block_height {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        require!(self.snapshots.get(low) == Some(block_height), "Block height is not a snapshot");
+    }
+
+    /// Storage of the history and its first checkpoint, added to the storage paid on registration.
+    /// Checkpoints added by later snapshots are covered by the contract.
+    pub(crate) fn measure_checkpoint_storage_usage(&mut self) -> StorageUsage {
+        let tmp_account_id: AccountId = "a".repeat(64).parse().unwrap();
+        let initial_storage_usage = env::storage_usage();
+        let mut checkpoints = self.account_checkpoints(&tmp_account_id);
+        checkpoints.checkpoints.push(&Checkpoint { block_height: 0, balance: 0 });
+        self.balance_checkpoints.insert(&tmp_account_id, &checkpoints);
+        let checkpoint_storage_usage = env::storage_usage() - initial_storage_usage;
+
+        checkpoints.clear();
+        self.balance_checkpoints.remove(&tmp_account_id);
+        return checkpoint_storage_usage;
+    }
+
+    /// Records the current balances of the registered `account_ids` and the current total supply
+    pub(crate) fn internal_checkpoint(&mut self, account_ids: &[&AccountId]) {
+        let last_snapshot = self.last_snapshot();
+        for account_id in account_ids {
+            let Some(balance) = self.token.accounts.get(account_id) else {
+                continue;
+            };
+            let mut checkpoints = self.account_checkpoints(account_id);
+            checkpoints.write(balance, last_snapshot);
+            self.balance_checkpoints.insert(account_id, &checkpoints);
+        }
+
+        self.supply_checkpoints.write(self.token.total_supply, last_snapshot);
+    }
+
+    /// Frees the history of an unregistered account, its past balances are no longer queryable
+    pub(crate) fn internal_remove_checkpoints(&mut self, account_id: &AccountId) {
+        if let Some(mut checkpoints) = self.balance_checkpoints.remove(account_id) {
+            checkpoints.clear();
+        }
+        self.supply_checkpoints.write(self.token.total_supply, self.last_snapshot());
+    }
+}
+
+#[near]
+impl TokenContract {
+    /// Takes a snapshot of every balance and of the total supply at the end of the current block,
+    /// only callable by the owner. Returns the block height to query with `ft_balance_at`.
+    #[payable]
+    pub fn create_snapshot(&mut self) -> u64 {
+        assert_one_yocto();
+        self.assert_owner();
+        let block_height = env::block_height();
+        if self.last_snapshot() != Some(block_height) {
+            self.snapshots.push(&block_height);
+            TokenEvent::SnapshotCreate { block_height }.emit();
+        }
+        return block_height;
+    }
+
+    pub fn get_snapshots(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<u64> {
+        let from_index = from_index.unwrap_or(0);
+        let limit = limit.unwrap_or(self.snapshots.len());
+        return (from_index..self.snapshots.len()).take(limit as usize).map(|index| self.snapshots.get(index).unwrap()).collect();
+    }
+
+    /// Balance of `account_id` at the end of `block_height`, which must be a past snapshot
+    pub fn ft_balance_at(&self, account_id: AccountId, block_height: u64) -> U128 {
+        self.assert_snapshot(block_height);
+        return self.account_checkpoints(&account_id).balance_at(block_height).into();
+    }
+
+    /// Total supply at the end of `block_height`, which must be a past snapshot
+    pub fn ft_total_supply_at(&self, block_height: u64) -> U128 {
+        self.assert_snapshot(block_height);
+        return self.supply_checkpoints.balance_at(block_height).into();
+    }
+}
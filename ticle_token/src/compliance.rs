@@ -0,0 +1,67 @@
+use near_sdk::{assert_one_yocto, env, near, require, AccountId};
+
+use crate::events::TokenEvent;
+use crate::token::{TokenContract, TokenContractExt};
+
+impl TokenContract {
+    pub(crate) fn assert_not_frozen(&self, account_id: &AccountId) {
+        require!(!self.frozen_accounts.contains(account_id), format!("Account {} is frozen", account_id));
+    }
+
+    fn assert_compliance_officer(&self) {
+        require!(self.compliance_officers.contains(&env::predecessor_account_id()), "Only compliance officer can call this method");
+    }
+}
+
+#[near]
+impl TokenContract {
+    #[payable]
+    pub fn add_compliance_officer(&mut self, account_id: AccountId) {
+        assert_one_yocto();
+        self.assert_owner();
+        require!(self.compliance_officers.insert(&account_id), "Already a compliance officer");
+        TokenEvent::ComplianceOfficerAdd { account_id }.emit();
+    }
+
+    #[payable]
+    pub fn remove_compliance_officer(&mut self, account_id: AccountId) {
+        assert_one_yocto();
+        self.assert_owner();
+        require!(self.compliance_officers.remove(&account_id), "Not a compliance officer");
+        TokenEvent::ComplianceOfficerRemove { account_id }.emit();
+    }
+
+    /// Blocks every transfer to and from `account_id`, e.g. after its keys were stolen
+    #[payable]
+    pub fn freeze_account(&mut self, account_id: AccountId, reason: Option<String>) {
+        assert_one_yocto();
+        self.assert_compliance_officer();
+        require!(self.frozen_accounts.insert(&account_id), "Account is already frozen");
+
+        TokenEvent::AccountFreeze {
+            account_id,
+            frozen_by: env::predecessor_account_id(),
+            reason,
+        }.emit();
+    }
+
+    #[payable]
+    pub fn unfreeze_account(&mut self, account_id: AccountId) {
+        assert_one_yocto();
+        self.assert_compliance_officer();
+        require!(self.frozen_accounts.remove(&account_id), "Account is not frozen");
+
+        TokenEvent::AccountUnfreeze {
+            account_id,
+            unfrozen_by: env::predecessor_account_id(),
+        }.emit();
+    }
+
+    pub fn is_frozen(&self, account_id: AccountId) -> bool {
+        return self.frozen_accounts.contains(&account_id);
+    }
+
+    pub fn get_compliance_officers(&self) -> Vec<AccountId> {
+        return self.compliance_officers.to_vec();
+    }
+}
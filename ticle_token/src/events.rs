@@ -0,0 +1,74 @@
+use near_sdk::json_types::{Base64VecU8, U128};
+use near_sdk::{near, AccountId};
+
+#[near(event_json(standard = "ticle_token"))]
+pub enum TokenEvent {
+    #[event_version("1.0.0")]
+    FtMetadataUpdate {
+        updated_by: AccountId,
+        icon: Option<String>,
+        reference: Option<String>,
+        reference_hash: Option<Base64VecU8>,
+    },
+    #[event_version("1.0.0")]
+    MinterAdd {
+        account_id: AccountId,
+    },
+    #[event_version("1.0.0")]
+    MinterRemove {
+        account_id: AccountId,
+    },
+    #[event_version("1.0.0")]
+    BurnApprove {
+        owner_id: AccountId,
+        spender_id: AccountId,
+        amount: U128,
+    },
+    #[event_version("1.0.0")]
+    FtApprove {
+        owner_id: AccountId,
+        spender_id: AccountId,
+        amount: U128,
+        expires_at: Option<u64>,
+    },
+    #[event_version("1.0.0")]
+    TreasuryUpdate {
+        treasury_id: Option<AccountId>,
+    },
+    /// The owner marked (`is_vesting: true`) or unmarked an account protected from unregistration
+    #[event_version("1.0.0")]
+    VestingAccountUpdate {
+        account_id: AccountId,
+        is_vesting: bool,
+    },
+    /// A vesting account opened (`is_open: true`) or closed a grant of `beneficiary_id`
+    #[event_version("1.0.0")]
+    VestingGrantUpdate {
+        vesting_id: AccountId,
+        beneficiary_id: AccountId,
+        is_open: bool,
+    },
+    #[event_version("1.0.0")]
+    ComplianceOfficerAdd {
+        account_id: AccountId,
+    },
+    #[event_version("1.0.0")]
+    ComplianceOfficerRemove {
+        account_id: AccountId,
+    },
+    #[event_version("1.0.0")]
+    AccountFreeze {
+        account_id: AccountId,
+        frozen_by: AccountId,
+        reason: Option<String>,
+    },
+    #[event_version("1.0.0")]
+    AccountUnfreeze {
+        account_id: AccountId,
+        unfrozen_by: AccountId,
+    },
+    #[event_version("1.0.0")]
+    SnapshotCreate {
+        block_height: u64,
+    },
+}
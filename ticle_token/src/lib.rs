@@ -1,3 +1,11 @@
+#![allow(clippy::needless_return)]
+
+pub mod allowance;
+pub mod batch;
+pub mod burn;
+pub mod checkpoint;
+pub mod compliance;
+pub mod events;
 pub mod token;
 
 
@@ -5,7 +13,13 @@ pub mod token;
 mod tests {
     use super::*;
     use near_contract_standards::fungible_token::metadata::{FungibleTokenMetadata, FT_METADATA_SPEC};
-    use near_sdk::test_utils::accounts;
+    use near_contract_standards::fungible_token::metadata::FungibleTokenMetadataProvider;
+    use near_contract_standards::fungible_token::resolver::FungibleTokenResolver;
+    use near_contract_standards::fungible_token::FungibleTokenCore;
+    use near_contract_standards::storage_management::StorageManagement;
+    use near_sdk::json_types::{Base64VecU8, U128};
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::{env, serde_json, test_vm_config, testing_env, AccountId, Gas, NearToken, PromiseResult, RuntimeFeesConfig};
 
     #[test]
     fn test_token() {
@@ -20,7 +34,602 @@ mod tests {
             reference_hash: None,
             decimals: 24,
         };
-        let contract = token::TokenContract::new(accounts(1).into(), total_supply, metadata);
+        let contract = token::TokenContract::new(accounts(1), total_supply, metadata, total_supply);
         assert_eq!(contract.metadata.get().unwrap().name, "Binance");
     }
-}
\ No newline at end of file
+
+    fn tic_metadata() -> FungibleTokenMetadata {
+        return FungibleTokenMetadata {
+            spec: FT_METADATA_SPEC.to_string(),
+            name: "T Token".to_string(),
+            symbol: "TIC".to_string(),
+            icon: None,
+            reference: None,
+            reference_hash: None,
+            decimals: 24,
+        };
+    }
+
+    #[test]
+    fn test_burn() {
+        let mut contract = token::TokenContract::new(accounts(1), 100.into(), tic_metadata(), 1000.into());
+        contract.token.internal_register_account(&accounts(2));
+        contract.token.internal_transfer(&accounts(1), &accounts(2), 40, None);
+
+        // The caller is burned from, not the transaction signer
+        testing_env!(VMContextBuilder::new()
+            .signer_account_id(accounts(1))
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.burn(10.into(), None);
+
+        assert_eq!(contract.ft_balance_of(accounts(1)), 60.into());
+        assert_eq!(contract.ft_balance_of(accounts(2)), 30.into());
+        assert_eq!(contract.ft_total_supply(), 90.into());
+        assert_eq!(contract.ft_total_burned(), 10.into());
+    }
+
+    #[test]
+    fn test_burn_from() {
+        let mut contract = token::TokenContract::new(accounts(1), 100.into(), tic_metadata(), 1000.into());
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(NearToken::from_millinear(10))
+            .build());
+        contract.approve_burn(accounts(2), 30.into());
+        assert_eq!(contract.burn_allowance(accounts(1), accounts(2)), 30.into());
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.burn_from(accounts(1), 20.into(), Some("settlement".to_string()));
+
+        assert_eq!(contract.ft_balance_of(accounts(1)), 80.into());
+        assert_eq!(contract.burn_allowance(accounts(1), accounts(2)), 10.into());
+        assert_eq!(contract.ft_total_burned(), 20.into());
+    }
+
+    #[test]
+    #[should_panic(expected = "Burn amount exceeds the allowance")]
+    fn test_burn_from_exceeds_allowance() {
+        let mut contract = token::TokenContract::new(accounts(1), 100.into(), tic_metadata(), 1000.into());
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.burn_from(accounts(1), 1.into(), None);
+    }
+
+    #[test]
+    fn test_update_ft_metadata() {
+        let mut contract = token::TokenContract::new(accounts(1), 100.into(), tic_metadata(), 1000.into());
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        let reference_hash = Base64VecU8::from(env::sha256(b"reference"));
+        contract.update_ft_metadata(Some("data:image/svg+xml,icon".to_string()), Some("https://ticle.io/tic.json".to_string()), Some(reference_hash.clone()));
+
+        let metadata = contract.ft_metadata();
+        assert_eq!(metadata.name, "T Token");
+        assert_eq!(metadata.icon, Some("data:image/svg+xml,icon".to_string()));
+        assert_eq!(metadata.reference, Some("https://ticle.io/tic.json".to_string()));
+        assert_eq!(metadata.reference_hash, Some(reference_hash));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only owner can call this method")]
+    fn test_update_ft_metadata_not_owner() {
+        let mut contract = token::TokenContract::new(accounts(1), 100.into(), tic_metadata(), 1000.into());
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.update_ft_metadata(None, None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Hash has to be 32 bytes")]
+    fn test_update_ft_metadata_invalid_hash() {
+        let mut contract = token::TokenContract::new(accounts(1), 100.into(), tic_metadata(), 1000.into());
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.update_ft_metadata(None, Some("https://ticle.io/tic.json".to_string()), Some(Base64VecU8::from(vec![0; 16])));
+    }
+
+    #[test]
+    fn test_mint() {
+        let mut contract = token::TokenContract::new(accounts(1), 100.into(), tic_metadata(), 1000.into());
+        contract.token.internal_register_account(&accounts(3));
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.add_minter(accounts(2));
+        assert_eq!(contract.get_minters(), vec![accounts(2)]);
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.mint(accounts(3), 300.into(), Some("reviewer emission".to_string()));
+
+        assert_eq!(contract.ft_balance_of(accounts(3)), 300.into());
+        assert_eq!(contract.ft_total_supply(), 400.into());
+        assert_eq!(contract.ft_max_supply(), 1000.into());
+        assert_eq!(contract.ft_total_minted(), 400.into());
+        assert_eq!(contract.ft_mintable_supply(), 600.into());
+
+        // Burned tokens stay counted against the cap
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.burn(100.into(), None);
+        assert_eq!(contract.ft_total_supply(), 300.into());
+        assert_eq!(contract.ft_mintable_supply(), 600.into());
+    }
+
+    #[test]
+    #[should_panic(expected = "Mint amount exceeds the max supply")]
+    fn test_mint_exceeds_max_supply() {
+        let mut contract = token::TokenContract::new(accounts(1), 100.into(), tic_metadata(), 1000.into());
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.add_minter(accounts(1));
+        contract.mint(accounts(1), 901.into(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only minter can mint")]
+    fn test_mint_not_minter() {
+        let mut contract = token::TokenContract::new(accounts(1), 100.into(), tic_metadata(), 1000.into());
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.mint(accounts(1), 1.into(), None);
+    }
+
+    #[test]
+    fn test_transfer_from() {
+        let mut contract = token::TokenContract::new(accounts(1), 100.into(), tic_metadata(), 1000.into());
+        contract.token.internal_register_account(&accounts(3));
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(NearToken::from_millinear(10))
+            .block_timestamp(1_000_000_000)
+            .build());
+        contract.ft_approve(accounts(2), 50.into(), Some(2_000));
+        assert_eq!(contract.ft_allowance(accounts(1), accounts(2)), 50.into());
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .block_timestamp(1_500_000_000)
+            .build());
+        contract.ft_transfer_from(accounts(1), accounts(3), 30.into(), None);
+
+        assert_eq!(contract.ft_balance_of(accounts(1)), 70.into());
+        assert_eq!(contract.ft_balance_of(accounts(3)), 30.into());
+        assert_eq!(contract.ft_allowance(accounts(1), accounts(2)), 20.into());
+
+        // The allowance can't be spent after it expires
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .block_timestamp(2_000_000_000)
+            .build());
+        assert_eq!(contract.ft_allowance(accounts(1), accounts(2)), 0.into());
+    }
+
+    #[test]
+    #[should_panic(expected = "Allowance expired")]
+    fn test_transfer_from_expired() {
+        let mut contract = token::TokenContract::new(accounts(1), 100.into(), tic_metadata(), 1000.into());
+        contract.token.internal_register_account(&accounts(3));
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(NearToken::from_millinear(10))
+            .build());
+        contract.ft_approve(accounts(2), 50.into(), Some(1_000));
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .block_timestamp(1_000_000_000)
+            .build());
+        contract.ft_transfer_from(accounts(1), accounts(3), 10.into(), None);
+    }
+
+    /// Takes a snapshot at `block_height` as the owner accounts(1)
+    fn snapshot(contract: &mut token::TokenContract, block_height: u64) {
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .block_height(block_height)
+            .storage_usage(env::storage_usage())
+            .build());
+        assert_eq!(contract.create_snapshot(), block_height);
+    }
+
+    #[test]
+    fn test_balance_checkpoints() {
+        testing_env!(VMContextBuilder::new().block_height(10).build());
+        let mut contract = token::TokenContract::new(accounts(1), 100.into(), tic_metadata(), 1000.into());
+        contract.token.internal_register_account(&accounts(2));
+        snapshot(&mut contract, 10);
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .block_height(20)
+            .build());
+        contract.ft_transfer(accounts(2), 30.into(), None);
+        contract.ft_transfer(accounts(2), 10.into(), None);
+        snapshot(&mut contract, 20);
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .block_height(30)
+            .build());
+        contract.burn(15.into(), None);
+        snapshot(&mut contract, 40);
+
+        testing_env!(VMContextBuilder::new().block_height(101).build());
+        assert_eq!(contract.get_snapshots(None, None), vec![10, 20, 40]);
+        assert_eq!(contract.ft_balance_at(accounts(1), 10), 100.into());
+        assert_eq!(contract.ft_balance_at(accounts(1), 20), 60.into());
+        assert_eq!(contract.ft_balance_at(accounts(2), 10), 0.into());
+        assert_eq!(contract.ft_balance_at(accounts(2), 20), 40.into());
+        assert_eq!(contract.ft_balance_at(accounts(2), 40), 25.into());
+        assert_eq!(contract.ft_total_supply_at(20), 100.into());
+        assert_eq!(contract.ft_total_supply_at(40), 85.into());
+    }
+
+    #[test]
+    #[should_panic(expected = "Block height must be in the past")]
+    fn test_balance_at_current_block() {
+        testing_env!(VMContextBuilder::new().block_height(10).build());
+        let mut contract = token::TokenContract::new(accounts(1), 100.into(), tic_metadata(), 1000.into());
+        snapshot(&mut contract, 10);
+        contract.ft_balance_at(accounts(1), 10);
+    }
+
+    #[test]
+    #[should_panic(expected = "Block height is not a snapshot")]
+    fn test_balance_at_block_without_snapshot() {
+        testing_env!(VMContextBuilder::new().block_height(10).build());
+        let mut contract = token::TokenContract::new(accounts(1), 100.into(), tic_metadata(), 1000.into());
+        snapshot(&mut contract, 10);
+
+        testing_env!(VMContextBuilder::new().block_height(20).build());
+        contract.ft_balance_at(accounts(1), 15);
+    }
+
+    /// Transfers 1 from accounts(1) to accounts(2) in each block of `block_heights`
+    fn transfer_per_block(contract: &mut token::TokenContract, block_heights: std::ops::Range<u64>) {
+        for block_height in block_heights {
+            testing_env!(VMContextBuilder::new()
+                .predecessor_account_id(accounts(1))
+                .attached_deposit(NearToken::from_yoctonear(1))
+                .block_height(block_height)
+                .storage_usage(env::storage_usage())
+                .build());
+            contract.ft_transfer(accounts(2), 1.into(), None);
+        }
+    }
+
+    #[test]
+    fn test_checkpoints_between_snapshots() {
+        testing_env!(VMContextBuilder::new().block_height(1).build());
+        let mut contract = token::TokenContract::new(accounts(1), 100.into(), tic_metadata(), 1000.into());
+        contract.token.internal_register_account(&accounts(2));
+        snapshot(&mut contract, 5);
+
+        // Dust sent in many blocks after a snapshot neither grows the history nor changes the snapshot
+        transfer_per_block(&mut contract, 10..11);
+        let storage_usage = env::storage_usage();
+        transfer_per_block(&mut contract, 11..60);
+        assert_eq!(env::storage_usage(), storage_usage);
+        snapshot(&mut contract, 60);
+        transfer_per_block(&mut contract, 70..80);
+
+        testing_env!(VMContextBuilder::new().block_height(100).build());
+        assert_eq!(contract.ft_balance_at(accounts(2), 5), 0.into());
+        assert_eq!(contract.ft_balance_at(accounts(1), 5), 100.into());
+        assert_eq!(contract.ft_balance_at(accounts(2), 60), 50.into());
+        assert_eq!(contract.ft_balance_at(accounts(1), 60), 50.into());
+        assert_eq!(contract.ft_balance_of(accounts(2)), 60.into());
+    }
+
+    #[test]
+    fn test_checkpoint_unregistered_account() {
+        let mut contract = token::TokenContract::new(accounts(1), 100.into(), tic_metadata(), 1000.into());
+        contract.internal_checkpoint(&[&accounts(2)]);
+        assert!(!contract.balance_checkpoints.contains_key(&accounts(2)));
+    }
+
+    fn frozen_contract() -> token::TokenContract {
+        let mut contract = token::TokenContract::new(accounts(1), 100.into(), tic_metadata(), 1000.into());
+        contract.token.internal_register_account(&accounts(2));
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.add_compliance_officer(accounts(3));
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.freeze_account(accounts(2), Some("Compromised keys".to_string()));
+        return contract;
+    }
+
+    #[test]
+    fn test_freeze_account() {
+        let mut contract = frozen_contract();
+        assert!(contract.is_frozen(accounts(2)));
+        assert_eq!(contract.get_compliance_officers(), vec![accounts(3)]);
+
+        contract.unfreeze_account(accounts(2));
+        assert!(!contract.is_frozen(accounts(2)));
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.ft_transfer(accounts(2), 30.into(), None);
+        assert_eq!(contract.ft_balance_of(accounts(2)), 30.into());
+    }
+
+    #[test]
+    #[should_panic(expected = "is frozen")]
+    fn test_transfer_to_frozen_account() {
+        let mut contract = frozen_contract();
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.ft_transfer(accounts(2), 30.into(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Account charlie is frozen")]
+    fn test_transfer_call_from_frozen_account() {
+        let mut contract = frozen_contract();
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .prepaid_gas(Gas::from_tgas(300))
+            .build());
+        contract.ft_transfer_call(accounts(1), 10.into(), None, "".to_string());
+    }
+
+    #[test]
+    fn test_resolve_transfer_to_frozen_receiver() {
+        let mut contract = token::TokenContract::new(accounts(1), 100.into(), tic_metadata(), 1000.into());
+        contract.token.internal_register_account(&accounts(2));
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .prepaid_gas(Gas::from_tgas(300))
+            .build());
+        contract.ft_transfer_call(accounts(2), 30.into(), None, "".to_string());
+        contract.add_compliance_officer(accounts(3));
+
+        // The receiver is frozen while the transfer is in flight
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.freeze_account(accounts(2), None);
+
+        // The unused amount stays with the frozen receiver instead of being refunded
+        let context = VMContextBuilder::new().predecessor_account_id(accounts(0)).current_account_id(accounts(0)).build();
+        let unused_amount = serde_json::to_vec(&U128(30)).unwrap();
+        testing_env!(context, test_vm_config(), RuntimeFeesConfig::test(), Default::default(), vec![PromiseResult::Successful(unused_amount)]);
+        assert_eq!(contract.ft_resolve_transfer(accounts(1), accounts(2), 30.into()), 30.into());
+        assert_eq!(contract.ft_balance_of(accounts(1)), 70.into());
+        assert_eq!(contract.ft_balance_of(accounts(2)), 30.into());
+    }
+
+    #[test]
+    #[should_panic(expected = "Only compliance officer can call this method")]
+    fn test_freeze_account_not_officer() {
+        let mut contract = token::TokenContract::new(accounts(1), 100.into(), tic_metadata(), 1000.into());
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.freeze_account(accounts(2), None);
+    }
+
+    #[test]
+    fn test_batch_transfer() {
+        let mut contract = token::TokenContract::new(accounts(1), 100.into(), tic_metadata(), 1000.into());
+        contract.token.internal_register_account(&accounts(2));
+        contract.token.internal_register_account(&accounts(3));
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.ft_batch_transfer(vec![accounts(2), accounts(3)], vec![30.into(), 20.into()], Some("payroll".to_string()));
+
+        assert_eq!(contract.ft_balance_of(accounts(1)), 50.into());
+        assert_eq!(contract.ft_balance_of(accounts(2)), 30.into());
+        assert_eq!(contract.ft_balance_of(accounts(3)), 20.into());
+    }
+
+    #[test]
+    #[should_panic(expected = "Receiver danny at index 1 is not registered")]
+    fn test_batch_transfer_unregistered_receiver() {
+        let mut contract = token::TokenContract::new(accounts(1), 100.into(), tic_metadata(), 1000.into());
+        contract.token.internal_register_account(&accounts(2));
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.ft_batch_transfer(vec![accounts(2), accounts(3)], vec![30.into(), 20.into()], None);
+    }
+
+    fn batch_transfer_call_contract(receiver_count: usize) -> token::TokenContract {
+        let mut contract = token::TokenContract::new(accounts(1), 100.into(), tic_metadata(), 1000.into());
+        let receiver_ids: Vec<AccountId> = (0..receiver_count).map(|index| format!("receiver{}.near", index).parse().unwrap()).collect();
+        for receiver_id in receiver_ids.iter() {
+            contract.token.internal_register_account(receiver_id);
+        }
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .prepaid_gas(Gas::from_tgas(300))
+            .build());
+        let amounts = receiver_ids.iter().map(|_| 10.into()).collect();
+        contract.ft_batch_transfer_call(receiver_ids, amounts, None, "".to_string());
+        return contract;
+    }
+
+    #[test]
+    fn test_batch_transfer_call_refunds_failed_receiver() {
+        let mut contract = batch_transfer_call_contract(2);
+        let (receiver0, receiver1): (AccountId, AccountId) = ("receiver0.near".parse().unwrap(), "receiver1.near".parse().unwrap());
+        assert_eq!(contract.ft_balance_of(accounts(1)), 80.into());
+
+        // The first receiver keeps everything, the call to the second one fails
+        let context = VMContextBuilder::new().predecessor_account_id(accounts(0)).current_account_id(accounts(0)).build();
+        let unused_amount = serde_json::to_vec(&U128(0)).unwrap();
+        testing_env!(context.clone(), test_vm_config(), RuntimeFeesConfig::test(), Default::default(), vec![PromiseResult::Successful(unused_amount)]);
+        assert_eq!(contract.ft_resolve_transfer(accounts(1), receiver0.clone(), 10.into()), 10.into());
+        testing_env!(context.clone(), test_vm_config(), RuntimeFeesConfig::test(), Default::default(), vec![PromiseResult::Failed]);
+        assert_eq!(contract.ft_resolve_transfer(accounts(1), receiver1.clone(), 10.into()), 0.into());
+
+        assert_eq!(contract.ft_balance_of(accounts(1)), 90.into());
+        assert_eq!(contract.ft_balance_of(receiver0), 10.into());
+        assert_eq!(contract.ft_balance_of(receiver1), 0.into());
+
+        let used_amounts = [U128(10), U128(0)].iter().map(|amount| PromiseResult::Successful(serde_json::to_vec(amount).unwrap())).collect();
+        testing_env!(context, test_vm_config(), RuntimeFeesConfig::test(), Default::default(), used_amounts);
+        assert_eq!(contract.callback_batch_transfer_call(), vec![U128(10), U128(0)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "At most 8 receivers can be called in one batch")]
+    fn test_batch_transfer_call_too_many_receivers() {
+        batch_transfer_call_contract(batch::MAX_BATCH_TRANSFER_CALL_RECEIVERS + 1);
+    }
+
+    fn unregister_contract() -> token::TokenContract {
+        let mut contract = token::TokenContract::new(accounts(1), 100.into(), tic_metadata(), 1000.into());
+        contract.token.internal_register_account(&accounts(2));
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.ft_transfer(accounts(2), 30.into(), None);
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        return contract;
+    }
+
+    #[test]
+    fn test_storage_unregister_burns_balance() {
+        let mut contract = unregister_contract();
+        assert!(contract.storage_unregister(Some(true)));
+
+        assert!(contract.storage_balance_of(accounts(2)).is_none());
+        assert!(contract.balance_checkpoints.get(&accounts(2)).is_none());
+        assert_eq!(contract.ft_total_supply(), 70.into());
+        assert_eq!(contract.ft_total_burned(), 30.into());
+    }
+
+    #[test]
+    fn test_storage_unregister_to_treasury() {
+        let mut contract = unregister_contract();
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.set_treasury(Some(accounts(1)));
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        assert!(contract.storage_unregister(Some(true)));
+
+        assert_eq!(contract.ft_balance_of(accounts(1)), 100.into());
+        assert_eq!(contract.ft_total_supply(), 100.into());
+        assert_eq!(contract.ft_total_burned(), 0.into());
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't unregister an account with active allowances")]
+    fn test_storage_unregister_with_allowance() {
+        let mut contract = unregister_contract();
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(NearToken::from_millinear(10))
+            .build());
+        contract.ft_approve(accounts(3), 10.into(), None);
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.storage_unregister(Some(true));
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't unregister an account with open vesting grants")]
+    fn test_storage_unregister_with_vesting_grant() {
+        let mut contract = unregister_contract();
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.set_vesting_account(accounts(3), true);
+
+        // The vesting contract reports a grant of accounts(2)
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.update_vesting_grant(accounts(2), true);
+        assert_eq!(contract.get_vesting_grant_count(accounts(2)), 1);
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.storage_unregister(Some(true));
+    }
+}
@@ -1,15 +1,32 @@
-use near_contract_standards::fungible_token::metadata::FungibleTokenMetadata;
-use near_contract_standards::fungible_token::{FungibleToken, FungibleTokenCore, FungibleTokenResolver};
+use near_contract_standards::fungible_token::events::{FtBurn, FtMint, FtTransfer};
+use near_contract_standards::fungible_token::metadata::{FungibleTokenMetadata, FungibleTokenMetadataProvider};
+use near_contract_standards::fungible_token::{Balance, FungibleToken, FungibleTokenCore, FungibleTokenResolver};
 use near_contract_standards::storage_management::{StorageBalance, StorageBalanceBounds, StorageManagement};
-use near_sdk::collections::LazyOption;
-use near_sdk::json_types::U128;
-use near_sdk::{env, log, near, AccountId, BorshStorageKey, NearToken, PanicOnDefault, PromiseOrValue};
+use near_sdk::collections::{LazyOption, LookupMap, LookupSet, UnorderedSet, Vector};
+use near_sdk::json_types::{Base64VecU8, U128};
+use near_sdk::{assert_one_yocto, env, log, near, require, AccountId, BorshStorageKey, NearToken, PanicOnDefault, Promise, PromiseOrValue};
+
+use crate::allowance::Allowance;
+use crate::checkpoint::CheckpointHistory;
+use crate::events::TokenEvent;
 
 #[derive(BorshStorageKey)]
 #[near]
-enum StorageKey {
+pub(crate) enum StorageKey {
     FungibleToken,
     Metadata,
+    Minters,
+    BurnAllowances,
+    Allowances,
+    BalanceCheckpoints,
+    AccountCheckpoints { account_hash: Vec<u8> },
+    SupplyCheckpoints,
+    ComplianceOfficers,
+    FrozenAccounts,
+    AllowanceCounts,
+    VestingAccounts,
+    Snapshots,
+    VestingGrants,
 }
 
 #[near(contract_state)]
@@ -17,35 +34,224 @@ enum StorageKey {
 pub struct TokenContract {
     pub token: FungibleToken,
     pub metadata: LazyOption<FungibleTokenMetadata>,
+    pub owner_id: AccountId,
+    pub minters: UnorderedSet<AccountId>,
+    /// Hard cap of all tokens ever minted, burned tokens don't free room to mint again
+    pub max_supply: Balance,
+    /// Tokens minted since deployment, including the initial supply
+    pub total_minted: Balance,
+    pub burn_allowances: LookupMap<(AccountId, AccountId), Balance>,
+    pub total_burned: Balance,
+    pub allowances: LookupMap<(AccountId, AccountId), Allowance>,
+    pub balance_checkpoints: LookupMap<AccountId, CheckpointHistory>,
+    pub supply_checkpoints: CheckpointHistory,
+    /// Block heights of the snapshots `ft_balance_at` can be queried at, in block order
+    pub snapshots: Vector<u64>,
+    pub compliance_officers: UnorderedSet<AccountId>,
+    pub frozen_accounts: LookupSet<AccountId>,
+    pub allowance_counts: LookupMap<AccountId, u32>,
+    /// Accounts the owner marked as holding unvested tokens, e.g. vesting contracts, which can't be unregistered.
+    /// Vesting contracts report the grants of their beneficiaries with `update_vesting_grant`.
+    pub vesting_accounts: LookupSet<AccountId>,
+    /// Number of grants reported open by vesting accounts per beneficiary, which can't be unregistered either
+    pub vesting_grants: LookupMap<AccountId, u32>,
+    /// Receives the balance of force-unregistered accounts instead of burning it
+    pub treasury_id: Option<AccountId>,
 }
 
 #[near]
 impl TokenContract {
     #[init]
-    pub fn new(owner_id: AccountId, total_supply: U128, metadata: FungibleTokenMetadata) -> Self {
+    pub fn new(owner_id: AccountId, total_supply: U128, metadata: FungibleTokenMetadata, max_supply: U128) -> Self {
         assert!(!env::state_exists(), "Already exists");
         metadata.assert_valid();
+        require!(total_supply.0 <= max_supply.0, "Total supply exceeds the max supply");
 
         let mut this = Self {
         token: FungibleToken::new(StorageKey::FungibleToken),
         metadata: LazyOption::new(StorageKey::Metadata, Some(&metadata)),
+        owner_id: owner_id.clone(),
+        minters: UnorderedSet::new(StorageKey::Minters),
+        max_supply: max_supply.0,
+        total_minted: total_supply.0,
+        burn_allowances: LookupMap::new(StorageKey::BurnAllowances),
+        total_burned: 0,
+        allowances: LookupMap::new(StorageKey::Allowances),
+        balance_checkpoints: LookupMap::new(StorageKey::BalanceCheckpoints),
+        supply_checkpoints: CheckpointHistory::new(StorageKey::SupplyCheckpoints),
+        snapshots: Vector::new(StorageKey::Snapshots),
+        compliance_officers: UnorderedSet::new(StorageKey::ComplianceOfficers),
+        frozen_accounts: LookupSet::new(StorageKey::FrozenAccounts),
+        allowance_counts: LookupMap::new(StorageKey::AllowanceCounts),
+        vesting_accounts: LookupSet::new(StorageKey::VestingAccounts),
+        vesting_grants: LookupMap::new(StorageKey::VestingGrants),
+        treasury_id: None,
         };
-        
+
+        // Registration also pays for the checkpoint history of the account
+        this.token.account_storage_usage += this.measure_checkpoint_storage_usage();
         this.token.internal_register_account(&owner_id);
         this.token.internal_deposit(&owner_id, total_supply.into());
+        this.internal_checkpoint(&[&owner_id]);
         
-        near_contract_standards::fungible_token::events::FtMint{
+        FtMint {
         owner_id: &owner_id,
-        amount: total_supply.into(),
+        amount: total_supply,
         memo: Some("Minted {amount} tokens"),
         }.emit();
         
         return this;
     }
 
+    pub(crate) fn assert_owner(&self) {
+        require!(env::predecessor_account_id() == self.owner_id, "Only owner can call this method");
+    }
+
+    /// Settles the storage change since `initial_storage_usage` against the attached deposit,
+    /// added storage is paid from the deposit and released storage is refunded with the rest of it
+    pub(crate) fn internal_settle_storage(&self, initial_storage_usage: u64) {
+        let storage_usage = env::storage_usage();
+        let attached_deposit = env::attached_deposit();
+        let refund = if storage_usage > initial_storage_usage {
+            let required_deposit = env::storage_byte_cost().saturating_mul((storage_usage - initial_storage_usage) as u128);
+            require!(attached_deposit >= required_deposit, format!("Must attach {} to cover storage", required_deposit));
+            attached_deposit.saturating_sub(required_deposit)
+        } else {
+            attached_deposit.saturating_add(env::storage_byte_cost().saturating_mul((initial_storage_usage - storage_usage) as u128))
+        };
+
+        if refund > NearToken::from_yoctonear(1) {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+    }
+
+    #[payable]
+    pub fn add_minter(&mut self, account_id: AccountId) {
+        assert_one_yocto();
+        self.assert_owner();
+        require!(self.minters.insert(&account_id), "Already a minter");
+        TokenEvent::MinterAdd { account_id }.emit();
+    }
+
+    #[payable]
+    pub fn remove_minter(&mut self, account_id: AccountId) {
+        assert_one_yocto();
+        self.assert_owner();
+        require!(self.minters.remove(&account_id), "Not a minter");
+        TokenEvent::MinterRemove { account_id }.emit();
+    }
+
+    /// Mints `amount` to a registered account, only callable by minters and capped by `max_supply`
+    #[payable]
+    pub fn mint(&mut self, account_id: AccountId, amount: U128, memo: Option<String>) {
+        assert_one_yocto();
+        require!(self.minters.contains(&env::predecessor_account_id()), "Only minter can mint");
+        require!(amount.0 > 0, "The amount should be a positive number");
+        require!(amount.0 <= self.ft_mintable_supply().0, "Mint amount exceeds the max supply");
+        self.assert_not_frozen(&account_id);
+
+        self.token.internal_deposit(&account_id, amount.into());
+        self.total_minted += amount.0;
+        self.internal_checkpoint(&[&account_id]);
+
+        FtMint {
+            owner_id: &account_id,
+            amount,
+            memo: memo.as_deref(),
+        }.emit();
+    }
+
+    pub fn get_minters(&self) -> Vec<AccountId> {
+        return self.minters.to_vec();
+    }
+
+    pub fn ft_max_supply(&self) -> U128 {
+        return self.max_supply.into();
+    }
+
+    pub fn ft_total_minted(&self) -> U128 {
+        return self.total_minted.into();
+    }
+
+    /// Room left under `max_supply`, burning tokens doesn't add to it
+    pub fn ft_mintable_supply(&self) -> U128 {
+        return (self.max_supply - self.total_minted).into();
+    }
+
+    #[payable]
+    pub fn set_treasury(&mut self, treasury_id: Option<AccountId>) {
+        assert_one_yocto();
+        self.assert_owner();
+        if let Some(treasury_id) = &treasury_id {
+            require!(self.token.accounts.contains_key(treasury_id), "Treasury is not registered");
+        }
+
+        self.treasury_id = treasury_id.clone();
+        TokenEvent::TreasuryUpdate { treasury_id }.emit();
+    }
+
+    /// Adds `account_id` to or removes it from the vesting accounts, only callable by the owner. The mark
+    /// protects the account from `storage_unregister` and lets a vesting contract report its grants, so it
+    /// should be removed once the account is fully vested.
+    #[payable]
+    pub fn set_vesting_account(&mut self, account_id: AccountId, is_vesting: bool) {
+        assert_one_yocto();
+        self.assert_owner();
+        if is_vesting {
+            self.vesting_accounts.insert(&account_id);
+        } else {
+            self.vesting_accounts.remove(&account_id);
+        }
+        TokenEvent::VestingAccountUpdate { account_id, is_vesting }.emit();
+    }
+
+    /// Called by a vesting account when a grant of `beneficiary_id` is created (`is_open: true`), and when it's
+    /// fully claimed or revoked. The beneficiary can't be unregistered while any of its grants is open.
+    pub fn update_vesting_grant(&mut self, beneficiary_id: AccountId, is_open: bool) {
+        let vesting_id = env::predecessor_account_id();
+        require!(self.vesting_accounts.contains(&vesting_id), "Only vesting account can update grants");
+
+        let count = self.vesting_grants.get(&beneficiary_id).unwrap_or(0);
+        let count = if is_open { count + 1 } else { count.saturating_sub(1) };
+        if count == 0 {
+            self.vesting_grants.remove(&beneficiary_id);
+        } else {
+            self.vesting_grants.insert(&beneficiary_id, &count);
+        }
+        TokenEvent::VestingGrantUpdate { vesting_id, beneficiary_id, is_open }.emit();
+    }
+
+    pub fn get_treasury(&self) -> Option<AccountId> {
+        return self.treasury_id.clone();
+    }
+
+    pub fn is_vesting_account(&self, account_id: AccountId) -> bool {
+        return self.vesting_accounts.contains(&account_id);
+    }
+
+    pub fn get_vesting_grant_count(&self, account_id: AccountId) -> u32 {
+        return self.vesting_grants.get(&account_id).unwrap_or(0);
+    }
+
+    /// Replaces the icon, reference and reference hash of the token metadata, only callable by the owner
     #[payable]
-    pub fn burn(&mut self, amount: U128) {
-        self.token.internal_withdraw(&env::signer_account_id(), amount.into());
+    pub fn update_ft_metadata(&mut self, icon: Option<String>, reference: Option<String>, reference_hash: Option<Base64VecU8>) {
+        assert_one_yocto();
+        self.assert_owner();
+
+        let mut metadata = self.metadata.get().unwrap();
+        metadata.icon = icon.clone();
+        metadata.reference = reference.clone();
+        metadata.reference_hash = reference_hash.clone();
+        metadata.assert_valid();
+        self.metadata.set(&metadata);
+
+        TokenEvent::FtMetadataUpdate {
+            updated_by: env::predecessor_account_id(),
+            icon,
+            reference,
+            reference_hash,
+        }.emit();
     }
 }
 
@@ -54,7 +260,10 @@ impl FungibleTokenCore for TokenContract {
     #[payable]
     fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>) {
         log!("receiver_id: {}", receiver_id);
-        self.token.ft_transfer(receiver_id, amount, memo)
+        self.assert_not_frozen(&env::predecessor_account_id());
+        self.assert_not_frozen(&receiver_id);
+        self.token.ft_transfer(receiver_id.clone(), amount, memo);
+        self.internal_checkpoint(&[&env::predecessor_account_id(), &receiver_id]);
     }
 
     #[payable]
@@ -65,7 +274,11 @@ impl FungibleTokenCore for TokenContract {
         memo: Option<String>,
         msg: String,
     ) -> PromiseOrValue<U128> {
-        self.token.ft_transfer_call(receiver_id, amount, memo, msg)
+        self.assert_not_frozen(&env::predecessor_account_id());
+        self.assert_not_frozen(&receiver_id);
+        let result = self.token.ft_transfer_call(receiver_id.clone(), amount, memo, msg);
+        self.internal_checkpoint(&[&env::predecessor_account_id(), &receiver_id]);
+        result
     }
 
     fn ft_total_supply(&self) -> U128 {
@@ -93,15 +306,51 @@ impl StorageManagement for TokenContract {
         self.token.storage_withdraw(amount)
     }
 
+    /// With `force` the remaining balance goes to the treasury when one is set, otherwise it's burned
     #[payable]
     fn storage_unregister(&mut self, force: Option<bool>) -> bool {
-        #[allow(unused_variables)]
-        if let Some((account_id, balance)) = self.token.internal_storage_unregister(force) {
-            log!("Closed @{} with {}", account_id, balance);
-            true
-        } else {
-            false
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        self.assert_not_frozen(&account_id);
+
+        let Some(balance) = self.token.accounts.get(&account_id) else {
+            log!("The account {} is not registered", account_id);
+            return false;
+        };
+        require!(balance == 0 || force.unwrap_or(false), "Can't unregister the account with the positive balance without force");
+        require!(!self.vesting_accounts.contains(&account_id), "Can't unregister a vesting account");
+        require!(!self.vesting_grants.contains_key(&account_id), "Can't unregister an account with open vesting grants");
+        require!(!self.allowance_counts.contains_key(&account_id), "Can't unregister an account with active allowances");
+
+        self.token.accounts.remove(&account_id);
+        self.token.total_supply -= balance;
+        if balance > 0 {
+            match self.treasury_id.clone().filter(|treasury_id| self.token.accounts.contains_key(treasury_id)) {
+                Some(treasury_id) => {
+                    self.token.internal_deposit(&treasury_id, balance);
+                    FtTransfer {
+                        old_owner_id: &account_id,
+                        new_owner_id: &treasury_id,
+                        amount: balance.into(),
+                        memo: Some("Balance of unregistered account"),
+                    }.emit();
+                    self.internal_checkpoint(&[&treasury_id]);
+                }
+                None => {
+                    self.total_burned += balance;
+                    FtBurn {
+                        owner_id: &account_id,
+                        amount: balance.into(),
+                        memo: Some("Balance of unregistered account"),
+                    }.emit();
+                }
+            }
         }
+        self.internal_remove_checkpoints(&account_id);
+
+        log!("Closed @{} with {}", account_id, balance);
+        Promise::new(account_id).transfer(self.storage_balance_bounds().min.saturating_add(NearToken::from_yoctonear(1)));
+        true
     }
 
     fn storage_balance_bounds(&self) -> StorageBalanceBounds {
@@ -121,10 +370,25 @@ impl FungibleTokenResolver for TokenContract {
         receiver_id: AccountId,
         amount: U128,
     ) -> U128 {
-        let (used_amount, burned_amount) = self.token.internal_ft_resolve_transfer(&sender_id, receiver_id, amount);
+        // Refunds would move tokens out of a receiver frozen while the transfer was in flight
+        if self.frozen_accounts.contains(&receiver_id) {
+            log!("Receiver @{} is frozen, the transfer is not refunded", receiver_id);
+            return amount;
+        }
+
+        let (used_amount, burned_amount) = self.token.internal_ft_resolve_transfer(&sender_id, receiver_id.clone(), amount);
+        self.internal_checkpoint(&[&sender_id, &receiver_id]);
         if burned_amount > 0 {
             log!("Account @{} burned {}", sender_id, burned_amount);
+            self.total_burned += burned_amount;
         }
         used_amount.into()
     }
-}
\ No newline at end of file
+}
+
+#[near]
+impl FungibleTokenMetadataProvider for TokenContract {
+    fn ft_metadata(&self) -> FungibleTokenMetadata {
+        self.metadata.get().unwrap()
+    }
+}
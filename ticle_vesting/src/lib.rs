@@ -0,0 +1,119 @@
+#![allow(clippy::needless_return)]
+
+pub mod vesting;
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+    use near_sdk::json_types::U128;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::{serde_json, testing_env, NearToken, PromiseOrValue};
+
+    const ONE_DAY: u64 = 24 * 60 * 60 * 1_000;
+
+    fn fund_grant(contract: &mut vesting::VestingContract, amount: u128, revocable: bool) -> U128 {
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(1))
+            .build());
+        let msg = serde_json::json!({
+            "beneficiary_id": accounts(2),
+            "start_timestamp": 0,
+            "cliff_duration": 30 * ONE_DAY,
+            "vesting_duration": 100 * ONE_DAY,
+            "revocable": revocable,
+        });
+        return match contract.ft_on_transfer(accounts(0), amount.into(), msg.to_string()) {
+            PromiseOrValue::Value(unused_amount) => unused_amount,
+            PromiseOrValue::Promise(_) => panic!("Unexpected promise"),
+        };
+    }
+
+    fn set_timestamp(predecessor_account_id: near_sdk::AccountId, timestamp_ms: u64) {
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(predecessor_account_id)
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .block_timestamp(timestamp_ms * 1_000_000)
+            .build());
+    }
+
+    #[test]
+    fn test_vesting_schedule() {
+        let mut contract = vesting::VestingContract::new(accounts(0), accounts(1));
+        assert_eq!(fund_grant(&mut contract, 1000, false), U128(0));
+
+        // Nothing vests before the cliff
+        set_timestamp(accounts(2), 29 * ONE_DAY);
+        assert_eq!(contract.get_vested_amount(accounts(2)), U128(0));
+
+        set_timestamp(accounts(2), 30 * ONE_DAY);
+        assert_eq!(contract.get_vested_amount(accounts(2)), U128(300));
+        assert_eq!(contract.get_unvested_amount(accounts(2)), U128(700));
+
+        contract.claim_vested();
+        assert_eq!(contract.get_claimable_amount(accounts(2)), U128(0));
+
+        set_timestamp(accounts(2), 120 * ONE_DAY);
+        assert_eq!(contract.get_vested_amount(accounts(2)), U128(1000));
+        assert_eq!(contract.get_claimable_amount(accounts(2)), U128(700));
+
+        // The final claim settles the grant, so the token lets the beneficiary unregister
+        contract.claim_vested();
+        assert!(contract.get_grant(accounts(2)).unwrap().is_settled(120 * ONE_DAY));
+    }
+
+    #[test]
+    fn test_vested_amount_at_token_scale() {
+        const ONE_TIC: u128 = 1_000_000_000_000_000_000_000_000;
+        const ONE_YEAR: u64 = 365 * ONE_DAY;
+        let grant = vesting::VestingGrant {
+            total_amount: U128(10_000_000 * ONE_TIC),
+            claimed_amount: U128(0),
+            start_timestamp: 1_700_000_000_000,
+            cliff_duration: 0,
+            vesting_duration: ONE_YEAR,
+            revocable: false,
+            revoked_timestamp: None,
+        };
+        assert_eq!(grant.vested_amount(grant.start_timestamp + ONE_YEAR / 4), 2_500_000 * ONE_TIC);
+        assert_eq!(grant.vested_amount(grant.start_timestamp + ONE_YEAR / 2), 5_000_000 * ONE_TIC);
+        assert_eq!(grant.vested_amount(grant.start_timestamp + ONE_YEAR), 10_000_000 * ONE_TIC);
+
+        // A cliff past the end of time never vests instead of overflowing
+        let grant = vesting::VestingGrant { cliff_duration: u64::MAX, vesting_duration: u64::MAX, ..grant };
+        assert_eq!(grant.vested_amount(u64::MAX), 0);
+    }
+
+    #[test]
+    fn test_duplicate_grant_is_refunded() {
+        let mut contract = vesting::VestingContract::new(accounts(0), accounts(1));
+        assert_eq!(fund_grant(&mut contract, 1000, false), U128(0));
+        assert_eq!(fund_grant(&mut contract, 500, false), U128(500));
+    }
+
+    #[test]
+    fn test_revoke() {
+        let mut contract = vesting::VestingContract::new(accounts(0), accounts(1));
+        fund_grant(&mut contract, 1000, true);
+
+        set_timestamp(accounts(0), 50 * ONE_DAY);
+        contract.revoke(accounts(2));
+        assert_eq!(contract.get_unallocated_amount(), U128(500));
+
+        // Vesting stops at the revocation
+        set_timestamp(accounts(2), 100 * ONE_DAY);
+        assert_eq!(contract.get_vested_amount(accounts(2)), U128(500));
+        assert_eq!(contract.get_unvested_amount(accounts(2)), U128(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Grant is not revocable")]
+    fn test_revoke_irrevocable() {
+        let mut contract = vesting::VestingContract::new(accounts(0), accounts(1));
+        fund_grant(&mut contract, 1000, false);
+
+        set_timestamp(accounts(0), 50 * ONE_DAY);
+        contract.revoke(accounts(2));
+    }
+}
@@ -0,0 +1,256 @@
+use near_contract_standards::fungible_token::core::ext_ft_core;
+use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+use near_contract_standards::fungible_token::Balance;
+use near_sdk::collections::LookupMap;
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{assert_one_yocto, env, ext_contract, log, near, require, serde_json, AccountId, BorshStorageKey, Gas, NearToken, PanicOnDefault, Promise, PromiseOrValue, PromiseResult};
+
+use ticle_math::mul_div;
+
+#[derive(BorshStorageKey)]
+#[near]
+enum StorageKey {
+    Grants,
+}
+
+/// Cliff + linear vesting schedule, timestamps and durations are in milliseconds
+#[near(serializers = [borsh, json])]
+pub struct VestingGrant {
+    pub total_amount: U128,
+    pub claimed_amount: U128,
+    pub start_timestamp: u64,
+    pub cliff_duration: u64,
+    pub vesting_duration: u64,
+    pub revocable: bool,
+    pub revoked_timestamp: Option<u64>,
+}
+
+impl VestingGrant {
+    pub fn vested_amount(&self, timestamp: u64) -> Balance {
+        let timestamp = self.revoked_timestamp.map_or(timestamp, |revoked_timestamp| revoked_timestamp.min(timestamp));
+        let Some(cliff_timestamp) = self.start_timestamp.checked_add(self.cliff_duration) else {
+            return 0;
+        };
+        if timestamp < cliff_timestamp {
+            return 0;
+        }
+
+        let elapsed = timestamp - self.start_timestamp;
+        if elapsed >= self.vesting_duration {
+            return self.total_amount.0;
+        }
+        // elapsed < vesting_duration, so the quotient is below total_amount and fits u128
+        return mul_div(self.total_amount.0, elapsed as u128, self.vesting_duration as u128).unwrap();
+    }
+
+    /// Tokens still to vest, a revoked grant has nothing left to vest
+    pub fn unvested_amount(&self, timestamp: u64) -> Balance {
+        if self.revoked_timestamp.is_some() {
+            return 0;
+        }
+        return self.total_amount.0 - self.vested_amount(timestamp);
+    }
+
+    /// Nothing is left to vest or to claim, the token no longer keeps the beneficiary from unregistering
+    pub fn is_settled(&self, timestamp: u64) -> bool {
+        return self.unvested_amount(timestamp) == 0 && self.claimed_amount.0 == self.vested_amount(timestamp);
+    }
+}
+
+#[ext_contract(ext_vesting_token)]
+pub trait VestingToken {
+    fn update_vesting_grant(&mut self, beneficiary_id: AccountId, is_open: bool);
+}
+
+/// `ft_transfer_call` message of the owner funding a grant
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CreateGrantMessage {
+    pub beneficiary_id: AccountId,
+    pub start_timestamp: u64,
+    pub cliff_duration: u64,
+    pub vesting_duration: u64,
+    pub revocable: bool,
+}
+
+#[near(contract_state)]
+#[derive(PanicOnDefault)]
+pub struct VestingContract {
+    pub owner_id: AccountId,
+    pub token_id: AccountId,
+    pub grants: LookupMap<AccountId, VestingGrant>,
+    /// Unvested tokens of revoked grants, withdrawable by the owner
+    pub unallocated_amount: Balance,
+}
+
+#[near]
+impl VestingContract {
+    #[init]
+    pub fn new(owner_id: AccountId, token_id: AccountId) -> Self {
+        return Self {
+            owner_id,
+            token_id,
+            grants: LookupMap::new(StorageKey::Grants),
+            unallocated_amount: 0,
+        };
+    }
+
+    fn internal_create_grant(&mut self, amount: Balance, message: CreateGrantMessage) -> Result<(), String> {
+        if self.grants.contains_key(&message.beneficiary_id) {
+            return Err(format!("Grant for {} already exists", message.beneficiary_id));
+        }
+        if message.vesting_duration == 0 || message.cliff_duration > message.vesting_duration {
+            return Err("Cliff duration must not exceed a positive vesting duration".to_string());
+        }
+        if message.start_timestamp.checked_add(message.vesting_duration).is_none() {
+            return Err("Vesting end timestamp overflows".to_string());
+        }
+
+        self.grants.insert(&message.beneficiary_id, &VestingGrant {
+            total_amount: amount.into(),
+            claimed_amount: U128(0),
+            start_timestamp: message.start_timestamp,
+            cliff_duration: message.cliff_duration,
+            vesting_duration: message.vesting_duration,
+            revocable: message.revocable,
+            revoked_timestamp: None,
+        });
+        self.internal_update_token_grant(&message.beneficiary_id, true);
+        return Ok(());
+    }
+
+    /// Reports a grant opened or settled to the token, which keeps the beneficiary registered meanwhile.
+    /// The contract has to be marked as a vesting account of the token.
+    fn internal_update_token_grant(&self, beneficiary_id: &AccountId, is_open: bool) {
+        ext_vesting_token::ext(self.token_id.clone())
+            .with_static_gas(Gas::from_tgas(5))
+            .update_vesting_grant(beneficiary_id.clone(), is_open);
+    }
+
+    /// Transfers claimed tokens to a beneficiary or unallocated tokens to the owner, restoring them on failure
+    fn internal_transfer(&self, receiver_id: &AccountId, amount: Balance, is_claim: bool) -> Promise {
+        return ext_ft_core::ext(self.token_id.clone())
+            .with_attached_deposit(NearToken::from_yoctonear(1))
+            .with_static_gas(Gas::from_tgas(20))
+            .ft_transfer(receiver_id.clone(), U128(amount), None)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(Gas::from_tgas(10))
+                    .callback_transfer(receiver_id, U128(amount), is_claim)
+            );
+    }
+
+    /// Transfers the vested and not yet claimed tokens to the caller
+    pub fn claim_vested(&mut self) -> Promise {
+        let beneficiary_id = env::predecessor_account_id();
+        let mut grant = self.grants.get(&beneficiary_id).expect("Grant not found");
+
+        let claimable_amount = grant.vested_amount(env::block_timestamp_ms()) - grant.claimed_amount.0;
+        require!(claimable_amount > 0, "Nothing to claim");
+
+        grant.claimed_amount = (grant.claimed_amount.0 + claimable_amount).into();
+        self.grants.insert(&beneficiary_id, &grant);
+        if grant.is_settled(env::block_timestamp_ms()) {
+            self.internal_update_token_grant(&beneficiary_id, false);
+        }
+
+        return self.internal_transfer(&beneficiary_id, claimable_amount, true);
+    }
+
+    /// Stops vesting of a revocable grant, the unvested tokens become withdrawable by the owner
+    #[payable]
+    pub fn revoke(&mut self, beneficiary_id: AccountId) {
+        assert_one_yocto();
+        require!(env::predecessor_account_id() == self.owner_id, "Only owner can revoke");
+
+        let mut grant = self.grants.get(&beneficiary_id).expect("Grant not found");
+        require!(grant.revocable, "Grant is not revocable");
+        require!(grant.revoked_timestamp.is_none(), "Grant is already revoked");
+
+        let timestamp = env::block_timestamp_ms();
+        let was_settled = grant.is_settled(timestamp);
+        self.unallocated_amount += grant.unvested_amount(timestamp);
+
+        grant.revoked_timestamp = Some(timestamp);
+        self.grants.insert(&beneficiary_id, &grant);
+        if !was_settled && grant.is_settled(timestamp) {
+            self.internal_update_token_grant(&beneficiary_id, false);
+        }
+    }
+
+    #[payable]
+    pub fn withdraw_unallocated(&mut self, amount: U128) -> Promise {
+        assert_one_yocto();
+        require!(env::predecessor_account_id() == self.owner_id, "Only owner can withdraw");
+        require!(amount.0 > 0 && amount.0 <= self.unallocated_amount, "Invalid amount");
+
+        self.unallocated_amount -= amount.0;
+        return self.internal_transfer(&self.owner_id, amount.0, false);
+    }
+
+    #[private]
+    pub fn callback_transfer(&mut self, receiver_id: &AccountId, amount: U128, is_claim: bool) {
+        const TRANSFER_PROMISE_INDEX: u64 = 0;
+        if let PromiseResult::Successful(_) = env::promise_result(TRANSFER_PROMISE_INDEX) {
+            return;
+        }
+
+        log!("[callback_transfer] transfer to {} failed, restoring {}", receiver_id, amount.0);
+        if is_claim {
+            let mut grant = self.grants.get(receiver_id).unwrap();
+            let was_settled = grant.is_settled(env::block_timestamp_ms());
+            grant.claimed_amount = (grant.claimed_amount.0 - amount.0).into();
+            self.grants.insert(receiver_id, &grant);
+            // The grant was reported settled by the failed claim, it's open again
+            if was_settled {
+                self.internal_update_token_grant(receiver_id, true);
+            }
+        } else {
+            self.unallocated_amount += amount.0;
+        }
+    }
+}
+
+#[near]
+impl VestingContract {
+    pub fn get_grant(&self, beneficiary_id: AccountId) -> Option<VestingGrant> {
+        return self.grants.get(&beneficiary_id);
+    }
+
+    pub fn get_vested_amount(&self, beneficiary_id: AccountId) -> U128 {
+        return self.grants.get(&beneficiary_id).map_or(0, |grant| grant.vested_amount(env::block_timestamp_ms())).into();
+    }
+
+    pub fn get_unvested_amount(&self, beneficiary_id: AccountId) -> U128 {
+        return self.grants.get(&beneficiary_id).map_or(0, |grant| grant.unvested_amount(env::block_timestamp_ms())).into();
+    }
+
+    pub fn get_claimable_amount(&self, beneficiary_id: AccountId) -> U128 {
+        return self.grants.get(&beneficiary_id).map_or(0, |grant| grant.vested_amount(env::block_timestamp_ms()) - grant.claimed_amount.0).into();
+    }
+
+    pub fn get_unallocated_amount(&self) -> U128 {
+        return self.unallocated_amount.into();
+    }
+}
+
+#[near]
+impl FungibleTokenReceiver for VestingContract {
+    /// Funds a new grant, returns the whole `amount` as unused when the grant can't be created
+    fn ft_on_transfer(&mut self, sender_id: AccountId, amount: U128, msg: String) -> PromiseOrValue<U128> {
+        require!(env::predecessor_account_id() == self.token_id, "Invalid token");
+        require!(sender_id == self.owner_id, "Only owner can fund grants");
+
+        let result = serde_json::from_str::<CreateGrantMessage>(&msg)
+            .map_err(|err| err.to_string())
+            .and_then(|message| self.internal_create_grant(amount.into(), message));
+
+        if let Err(err) = result {
+            log!("[ft_on_transfer] failed: {}", err);
+            return PromiseOrValue::Value(amount);
+        }
+
+        return PromiseOrValue::Value(U128(0));
+    }
+}